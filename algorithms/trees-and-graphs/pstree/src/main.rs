@@ -1,50 +1,260 @@
+use crossterm::terminal;
+use process_tree_filter::{Field, Filter, Filters};
+use process_tree_parser::AllProcessesTree;
+use process_tree_printer::{LineDrawing, PrintOptions};
+use process_tree_source::{Live, ProcessSource, SnapshotFile};
+use std::collections::HashMap;
 use std::env;
-use std::env::Args;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::time::Duration;
 
 mod process_tree_filter;
 mod process_tree_parser;
 mod process_tree_printer;
+mod process_tree_procfs;
+mod process_tree_snapshot;
+mod process_tree_source;
+mod process_tree_watch;
+
+const USAGE: &str = "Usage: color-pstree [-p PID] [-u USER] [-a] [-g] [-l] [-U | -A] [--no-color] \
+                     [-r] [--or] [--watch[=SECONDS]] [--snapshot FILE] [--save-snapshot FILE] [[-v] [FIELD=]PATTERN]...";
+
+struct Options {
+    // Only show the processes that match these (and their parents and children).
+    filters: Filters,
+    // Show the tree under this process, rather than the whole thing.
+    root_pid: Option<usize>,
+    // Don't cut lines off at the terminal's width.
+    long_lines: bool,
+    color: bool,
+    show_arguments: bool,
+    show_pgids: bool,
+    line_drawing: LineDrawing,
+    // Read processes from a snapshot file instead of the running system.
+    snapshot_to_load: Option<PathBuf>,
+    // Save the processes we read to a snapshot file, to replay later.
+    snapshot_to_save: Option<PathBuf>,
+    // Keep redrawing the tree, this often, until the user quits.
+    watch_interval: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            filters: Filters::default(),
+            root_pid: None,
+            long_lines: false,
+            color: true,
+            show_arguments: false,
+            show_pgids: false,
+            line_drawing: LineDrawing::Unicode,
+            snapshot_to_load: None,
+            snapshot_to_save: None,
+            watch_interval: None,
+        }
+    }
+}
+
+impl Options {
+    fn print_options(&self, max_width: Option<usize>) -> PrintOptions {
+        PrintOptions {
+            max_width,
+            color: self.color,
+            show_arguments: self.show_arguments,
+            show_pgids: self.show_pgids,
+            line_drawing: self.line_drawing,
+        }
+    }
+}
 
 fn main() {
-    // Optionally, the caller will have given us a string to filter processes by.
-    let filter_processes_by_text = parse_args(env::args());
-
-    let all_processes_tree = process_tree_parser::execute_ps_and_parse();
-    let all_processes_root = &all_processes_tree.get_root();
-
-    let parent_pids_to_child_processes = if let Some(filter_text) = &filter_processes_by_text {
-        // If we were given text to filter processes by, create a new filtered tree.
-        process_tree_filter::run(
-            filter_text,
-            all_processes_root,
-            &all_processes_tree.all_parent_pids_to_child_processes,
+    let options = parse_args(env::args().skip(1)); // skip zeroth arg, which is path to program
+
+    let process_source: Box<dyn ProcessSource> = match &options.snapshot_to_load {
+        Some(path) => Box::new(SnapshotFile(path.clone())),
+        None => Box::new(Live),
+    };
+    let all_processes_tree = process_source.load().expect("Failed to read the processes");
+    if let Some(path) = &options.snapshot_to_save {
+        process_tree_snapshot::save(&all_processes_tree, path).expect("Failed to save snapshot");
+    }
+    let all_processes_root = match options.root_pid {
+        Some(pid) => all_processes_tree.find(pid).unwrap_or_else(|| {
+            eprintln!("No process with PID {pid}");
+            std::process::exit(1);
+        }),
+        None => all_processes_tree.get_root(),
+    };
+
+    if let Some(interval) = options.watch_interval {
+        if !io::stdout().is_terminal() {
+            eprintln!("--watch needs a terminal to draw in");
+            std::process::exit(1);
+        }
+        process_tree_watch::run(
+            process_source.as_ref(),
+            all_processes_tree,
+            &options,
+            interval,
         )
-    } else {
-        // Otherwise just use the full tree.
-        all_processes_tree.all_parent_pids_to_child_processes
+        .expect("Failed to watch the processes");
+        return;
+    }
+
+    let Some((parent_pids_to_child_processes, root)) =
+        select_processes(&all_processes_tree, all_processes_root, &options)
+    else {
+        // Everything was filtered out, so there's nothing to print.
+        return;
     };
 
+    // If we're not in a terminal (we're being piped somewhere, say), there's no width to cut
+    // lines off at.
+    let max_width = if options.long_lines {
+        None
+    } else {
+        terminal::size().ok().map(|(columns, _)| columns as usize)
+    };
     process_tree_printer::print(
+        &mut io::stdout().lock(),
         &parent_pids_to_child_processes,
+        &root,
         all_processes_tree.max_num_pid_chars,
-        filter_processes_by_text,
+        &options.filters,
+        &options.print_options(max_width),
+    )
+    .expect("Failed to print to stdout");
+}
+
+/// Return the processes to print, and the one to start printing from. That's everything under
+/// the given root, unless we were given filters, in which case it's a new filtered tree of the
+/// processes that match them. Return None if nothing matched.
+fn select_processes(
+    all_processes_tree: &AllProcessesTree,
+    all_processes_root: &Process,
+    options: &Options,
+) -> Option<(HashMap<usize, Vec<Process>>, Process)> {
+    if options.filters.is_empty() {
+        return Some((
+            all_processes_tree
+                .all_parent_pids_to_child_processes
+                .clone(),
+            all_processes_root.clone(),
+        ));
+    }
+
+    let filtered_parent_pids_to_child_processes = process_tree_filter::run(
+        &|process| options.filters.matches(process),
+        all_processes_root,
+        &all_processes_tree.all_parent_pids_to_child_processes,
     );
+    let root = filtered_parent_pids_to_child_processes
+        .get(&ROOT_PARENT_PID)?
+        .first()?
+        .clone();
+    Some((filtered_parent_pids_to_child_processes, root))
 }
 
-fn parse_args(args: Args) -> Option<String> {
-    let mut skipped = args.skip(1); // skip zeroth arg, which is path to program
-    let filter_processes_by_text = skipped.next();
-    if skipped.next().is_some() {
-        panic!(
-            "Only one argument is allowed; it will be used to filter the displayed processes. \
-            To filter by a phrase containing whitespace, enclose the phrase in quotation marks."
-        )
+fn parse_args(args: impl IntoIterator<Item = String>) -> Options {
+    let mut options = Options::default();
+    let mut skipped = args.into_iter();
+    // Filters can't be parsed until we know whether they're regexes, which we might not find
+    // out until the end. So collect them first, with whether they were inverted.
+    let mut filter_args: Vec<(String, bool)> = Vec::new();
+    let mut is_regex = false;
+
+    while let Some(arg) = skipped.next() {
+        match arg.as_str() {
+            "-p" => {
+                options.root_pid = Some(
+                    skipped
+                        .next()
+                        .and_then(|pid| pid.parse().ok())
+                        .unwrap_or_else(|| usage_error("-p must be given a PID")),
+                )
+            }
+            "-u" => {
+                let user = skipped
+                    .next()
+                    .unwrap_or_else(|| usage_error("-u must be given a user"));
+                // Always the exact name, even with -r
+                let filter = Filter::new(Field::User, &user, false, false)
+                    .expect("An escaped user name is always a valid regex");
+                options.filters.user = Some(filter);
+            }
+            "-l" => options.long_lines = true,
+            "-a" => options.show_arguments = true,
+            "-g" => options.show_pgids = true,
+            "-U" => options.line_drawing = LineDrawing::Unicode,
+            "-A" => options.line_drawing = LineDrawing::Ascii,
+            "--no-color" => options.color = false,
+            "-r" | "--regex" => is_regex = true,
+            "--or" => options.filters.match_any = true,
+            "-v" => filter_args.push((
+                skipped
+                    .next()
+                    .unwrap_or_else(|| usage_error("-v must be given a filter")),
+                true,
+            )),
+            "--watch" => options.watch_interval = Some(DEFAULT_WATCH_INTERVAL),
+            // The interval has to be part of the same argument, or a number after `--watch`
+            // couldn't be told apart from a filter on a number.
+            _ if arg.starts_with("--watch=") => {
+                options.watch_interval = Some(
+                    arg["--watch=".len()..]
+                        .parse()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .filter(|interval| !interval.is_zero())
+                        .unwrap_or_else(|| {
+                            usage_error("--watch must be given a positive interval")
+                        }),
+                )
+            }
+            "--snapshot" => {
+                options.snapshot_to_load = Some(
+                    skipped
+                        .next()
+                        .unwrap_or_else(|| usage_error("--snapshot must be given a file"))
+                        .into(),
+                )
+            }
+            "--save-snapshot" => {
+                options.snapshot_to_save = Some(
+                    skipped
+                        .next()
+                        .unwrap_or_else(|| usage_error("--save-snapshot must be given a file"))
+                        .into(),
+                )
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("Unrecognized option '{arg}'")),
+            _ => filter_args.push((arg, false)),
+        }
     }
-    filter_processes_by_text
+
+    for (arg, inverted) in filter_args {
+        let filter = Filter::parse(&arg, is_regex, inverted)
+            .unwrap_or_else(|e| usage_error(&format!("Invalid filter '{arg}': {e}")));
+        options.filters.filters.push(filter);
+    }
+    options
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
 }
 
 const ROOT_PARENT_PID: usize = 0;
 
+// The same as `watch`
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
 #[derive(Clone)]
 pub struct Process {
     pub pid: usize,  // process ID
@@ -53,6 +263,21 @@ pub struct Process {
     pub command: String,
 }
 
+impl Process {
+    /// Just the name of the program, as the real pstree shows by default: the first word of
+    /// the command, without its directory. (Login shells put a '-' in front of their name,
+    /// which we leave off too.) Kernel threads have no command line, and `ps` shows their
+    /// names in brackets instead, which we keep whole.
+    pub fn name(&self) -> &str {
+        if self.command.starts_with('[') {
+            return &self.command;
+        }
+        let program = self.command.split_whitespace().next().unwrap_or_default();
+        let name = program.rsplit('/').next().unwrap_or_default();
+        name.strip_prefix('-').unwrap_or(name)
+    }
+}
+
 impl PartialEq for Process {
     /// Equality should only depend on PID, not on string values. We might have one copy of
     /// a process that has had some ANSI color codes added to its strings, and another copy
@@ -1,18 +1,146 @@
 use super::{Process, ROOT_PARENT_PID};
 
+use regex::Regex;
 use std::collections::HashMap;
+use std::ops::Range;
 
+/// The parts of a process that a filter can look at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Command,
+    User,
+    Pid,
+    Pgid,
+}
+
+impl Field {
+    fn value(self, process: &Process) -> String {
+        match self {
+            Field::Command => process.command.clone(),
+            Field::User => process.user.clone(),
+            Field::Pid => process.pid.to_string(),
+            Field::Pgid => process.pgid.to_string(),
+        }
+    }
+}
+
+/// One filter from the command line, which looks for a pattern in one field of each process.
+pub struct Filter {
+    field: Field,
+    pattern: Regex,
+    // Match the processes where the pattern _isn't_ found instead
+    inverted: bool,
+}
+
+impl Filter {
+    /// Parse a filter from the command line, which looks like "[FIELD=]PATTERN". The field is
+    /// one of `command` (the default), `user`, `pid` or `pgid`.
+    ///
+    /// Unless `is_regex` is set, the pattern is plain text. For a command, that's found anywhere
+    /// in it, ignoring case; for the other fields it has to be the whole thing, so that
+    /// "pid=1" doesn't match PID 512. A regex is used as written, and is found anywhere
+    /// unless it's anchored with ^ and $.
+    pub fn parse(arg: &str, is_regex: bool, inverted: bool) -> Result<Filter, regex::Error> {
+        let (field, pattern) = match arg.split_once('=') {
+            Some(("command", pattern)) => (Field::Command, pattern),
+            Some(("user", pattern)) => (Field::User, pattern),
+            Some(("pid", pattern)) => (Field::Pid, pattern),
+            Some(("pgid", pattern)) => (Field::Pgid, pattern),
+            _ => (Field::Command, arg),
+        };
+        Filter::new(field, pattern, is_regex, inverted)
+    }
+
+    pub fn new(
+        field: Field,
+        pattern: &str,
+        is_regex: bool,
+        inverted: bool,
+    ) -> Result<Filter, regex::Error> {
+        let pattern = match (is_regex, field) {
+            (true, _) => Regex::new(pattern)?,
+            (false, Field::Command) => Regex::new(&format!("(?i){}", regex::escape(pattern)))?,
+            (false, _) => Regex::new(&format!("^{}$", regex::escape(pattern)))?,
+        };
+        Ok(Filter {
+            field,
+            pattern,
+            inverted,
+        })
+    }
+
+    fn matches(&self, process: &Process) -> bool {
+        self.pattern.is_match(&self.field.value(process)) != self.inverted
+    }
+}
+
+/// All the filters from the command line, and how to combine them.
+#[derive(Default)]
+pub struct Filters {
+    pub filters: Vec<Filter>,
+    // A process only has to match one filter, rather than all of them
+    pub match_any: bool,
+    // From `-u`, which a process has to match however the other filters are combined
+    pub user: Option<Filter>,
+}
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.user.is_none()
+    }
+
+    pub fn matches(&self, process: &Process) -> bool {
+        let matches_user = self.user.as_ref().is_none_or(|user| user.matches(process));
+        // With only a user to match, `any` would find nothing
+        let matches_filters = if self.match_any && !self.filters.is_empty() {
+            self.filters.iter().any(|filter| filter.matches(process))
+        } else {
+            self.filters.iter().all(|filter| filter.matches(process))
+        };
+        matches_user && matches_filters
+    }
+
+    /// Find everything the filters match in some text, which is the given field of a process
+    /// as we're about to print it, so that it can be highlighted. Returns byte ranges of the
+    /// text, in order and without overlaps. (Inverted filters don't match anything we could
+    /// point to, so we leave them out.)
+    pub fn highlights(&self, field: Field, text: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .filters
+            .iter()
+            .chain(&self.user)
+            .filter(|filter| filter.field == field && !filter.inverted)
+            .flat_map(|filter| filter.pattern.find_iter(text))
+            .map(|found| found.range())
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_unstable_by_key(|range| range.start);
+
+        // Two filters may well match overlapping parts of the same text, like "log" and "login".
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// Keep only the processes that match, along with all of their parents and children. Whatever
+/// process we start from (the root of the tree, or whichever was given with `-p`) is keyed by
+/// ROOT_PARENT_PID in the filtered map.
 pub fn run(
-    filter_text: &str,
+    matches: &dyn Fn(&Process) -> bool,
     all_processes_root: &Process,
     all_parent_pids_to_child_processes: &HashMap<usize, Vec<Process>>,
 ) -> HashMap<usize, Vec<Process>> {
-    let filter_text_lowercased = filter_text.to_lowercase();
     let mut filtered_parent_pids_to_child_processes = HashMap::new();
     let parents: Vec<&Process> = Vec::new();
 
-    all_processes_root.filter_by_text_recursive(
-        &filter_text_lowercased,
+    all_processes_root.filter_recursive(
+        matches,
         &parents,
         all_parent_pids_to_child_processes,
         &mut filtered_parent_pids_to_child_processes,
@@ -22,11 +150,11 @@ pub fn run(
 }
 
 impl Process {
-    /// Filter processes by the given text (case-insensitive). Matching processes _and all of
-    /// their parents and children_ will be copied from the `all` map to the `filtered` map.
-    fn filter_by_text_recursive<'proc>(
+    /// Filter processes with the given function. Matching processes _and all of their parents
+    /// and children_ will be copied from the `all` map to the `filtered` map.
+    fn filter_recursive(
         &self,
-        lowercased_filter_text: &str,
+        matches: &dyn Fn(&Process) -> bool,
         parents: &Vec<&Process>,
         all_parent_pids_to_child_processes: &HashMap<usize, Vec<Process>>,
         filtered_parent_pids_to_child_processes: &mut HashMap<usize, Vec<Process>>,
@@ -41,10 +169,10 @@ impl Process {
             let parent_pid = parents.last().unwrap().pid;
             let entry = filtered_parent_pids_to_child_processes
                 .entry(parent_pid)
-                .or_insert_with(Vec::new);
+                .or_default();
             entry.push(self.clone());
             true
-        } else if self.command.to_lowercase().contains(lowercased_filter_text) {
+        } else if matches(self) {
             // This process matches our filter! Merge the process and its parents into our filtered map.
             // Note: since I left parent pid out of the process struct, we need to keep track of
             // it ourselves.
@@ -52,7 +180,7 @@ impl Process {
             for process in parents.iter().chain(std::iter::once(&self)) {
                 let entry = filtered_parent_pids_to_child_processes
                     .entry(parent_pid)
-                    .or_insert_with(Vec::new);
+                    .or_default();
                 // This `contains` check runs in O(n) time (not great), but these lists shouldn't be
                 // very long. If necessary, we could start to use a HashSet for O(1) lookup, and later
                 // convert the set to a list (sorted by process ID!).
@@ -72,9 +200,9 @@ impl Process {
             childs_parents.push(self);
 
             for child in children {
-                Self::filter_by_text_recursive(
+                Self::filter_recursive(
                     child,
-                    lowercased_filter_text,
+                    matches,
                     &childs_parents,
                     all_parent_pids_to_child_processes,
                     filtered_parent_pids_to_child_processes,
@@ -84,3 +212,99 @@ impl Process {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: usize, pgid: usize, user: &str, command: &str) -> Process {
+        Process {
+            pid,
+            pgid,
+            user: user.to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn plain_text_filters() {
+        let login = process(1601, 1601, "root", "login -pf jane.doe");
+        let matches = |arg| Filter::parse(arg, false, false).unwrap().matches(&login);
+
+        assert!(matches("LOGIN"));
+        assert!(matches("jane"));
+        assert!(matches("user=root"));
+        assert!(!matches("user=roo"));
+        assert!(matches("pid=1601"));
+        assert!(!matches("pid=16"));
+        assert!(matches("pgid=1601"));
+        // Not a field we know, so it's all part of the command
+        assert!(!matches("ppid=1"));
+    }
+
+    #[test]
+    fn regex_and_inverted_filters() {
+        let login = process(1601, 1601, "root", "login -pf jane.doe");
+        let matches = |arg, inverted| Filter::parse(arg, true, inverted).unwrap().matches(&login);
+
+        assert!(matches(r"^login\s+-pf", false));
+        assert!(!matches("^LOGIN", false));
+        assert!(matches("pid=^16", false));
+        assert!(matches("user=^r.*t$", false));
+        assert!(!matches("user=root", true));
+        assert!(matches("user=jane", true));
+        assert!(Filter::parse("(", true, false).is_err());
+    }
+
+    #[test]
+    fn all_or_any() {
+        let login = process(1601, 1601, "root", "login -pf jane.doe");
+        let mut filters = Filters {
+            filters: vec![
+                Filter::parse("login", false, false).unwrap(),
+                Filter::parse("user=jane.doe", false, false).unwrap(),
+            ],
+            match_any: false,
+            user: None,
+        };
+        assert!(!filters.matches(&login));
+        filters.match_any = true;
+        assert!(filters.matches(&login));
+    }
+
+    #[test]
+    fn user_is_required_even_with_or() {
+        let login = process(1601, 1601, "root", "login -pf jane.doe");
+        let mut filters = Filters {
+            filters: vec![Filter::parse("login", false, false).unwrap()],
+            match_any: true,
+            user: Some(Filter::new(Field::User, "jane.doe", false, false).unwrap()),
+        };
+        assert!(!filters.matches(&login));
+        filters.user = Some(Filter::new(Field::User, "root", false, false).unwrap());
+        assert!(filters.matches(&login));
+        assert_eq!(filters.highlights(Field::User, "root")[0], 0..4);
+
+        // On its own, it's all a process has to match
+        filters.filters.clear();
+        assert!(filters.matches(&login));
+    }
+
+    #[test]
+    fn every_match_is_highlighted() {
+        let filters = Filters {
+            filters: vec![
+                Filter::parse("log", false, false).unwrap(),
+                Filter::parse("login", false, false).unwrap(),
+                Filter::parse("window", false, true).unwrap(),
+            ],
+            match_any: false,
+            user: None,
+        };
+        assert_eq!(
+            filters.highlights(Field::Command, "loginwindow -- Log in"),
+            [0..5, 15..18]
+        );
+        assert!(filters.highlights(Field::User, "login").is_empty());
+    }
+}
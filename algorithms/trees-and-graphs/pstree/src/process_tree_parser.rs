@@ -2,6 +2,7 @@ use super::{Process, ROOT_PARENT_PID};
 
 use regex::Regex;
 use std::collections::HashMap;
+use std::io;
 use std::process::Command;
 use std::sync::OnceLock;
 
@@ -10,65 +11,78 @@ pub struct AllProcessesTree {
     pub max_num_pid_chars: usize,
 }
 
-pub fn execute_ps_and_parse() -> AllProcessesTree {
-    let ps_output = execute_ps();
-    parse(ps_output)
+pub fn execute_ps_and_parse() -> io::Result<AllProcessesTree> {
+    let ps_output = execute_ps()?;
+    Ok(parse(&ps_output))
 }
 
-fn execute_ps() -> String {
+fn execute_ps() -> io::Result<String> {
     let ps_stdout_bytes = Command::new("ps")
         .args(["-axwwo", "user,pid,ppid,pgid,command"]) // same args used by real pstree, I think
-        .output()
-        .expect("ps command failed")
+        .output()?
         .stdout;
-    String::from_utf8(ps_stdout_bytes).expect("ps failed to output valid utf-8")
+    String::from_utf8(ps_stdout_bytes).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "ps failed to output valid utf-8",
+        )
+    })
 }
 
-fn parse(ps_output: String) -> AllProcessesTree {
-    // To model a tree (graph where every child can have only one parent), we use a map
-    // of parent PID to process instance. We could do something more elaborate where each
-    // Process owns a Vec<Process> of its children, but that isn't necessary.
-    // NOTE: technically, we could print the tree line-by-line as we parse it; `ps` will
-    // give us perfectly-ordered processes. However, that wouldn't play well with the
-    // filtering that we sometimes want to do between parsing and printing.
-    let mut all_parent_pids_to_child_processes: HashMap<usize, Vec<Process>> = HashMap::new();
-    let mut max_pid = 0;
-
+fn parse(ps_output: &str) -> AllProcessesTree {
     // We use skip(1) to skip the first line, which just contains headers.
-    for ps_line in ps_output.lines().skip(1) {
-        let (process, parent_pid) = Process::from_ps_line(ps_line);
-        // Technically we don't HAVE to call `max`, lines are already sorted by PID.
-        max_pid = std::cmp::max(max_pid, process.pid);
-
-        all_parent_pids_to_child_processes
-            .entry(parent_pid)
-            .or_insert_with(Vec::new)
-            .push(process);
-    }
+    AllProcessesTree::from_processes(ps_output.lines().skip(1).map(Process::from_ps_line))
+}
 
-    // We'll want to left-pad every printed PID with zeroes until it matches the length
-    // of the largest PID.
-    let max_num_pid_chars = format!("{max_pid}").len();
+impl AllProcessesTree {
+    /// Build the tree from each process paired with its parent PID, in order of PID.
+    pub fn from_processes(processes: impl IntoIterator<Item = (Process, usize)>) -> Self {
+        // To model a tree (graph where every child can have only one parent), we use a map
+        // of parent PID to process instance. We could do something more elaborate where each
+        // Process owns a Vec<Process> of its children, but that isn't necessary.
+        // NOTE: technically, we could print the tree line-by-line as we parse it; `ps` will
+        // give us perfectly-ordered processes. However, that wouldn't play well with the
+        // filtering that we sometimes want to do between parsing and printing.
+        let mut all_parent_pids_to_child_processes: HashMap<usize, Vec<Process>> = HashMap::new();
+        let mut max_pid = 0;
+
+        for (process, parent_pid) in processes {
+            // Technically we don't HAVE to call `max`, processes are already sorted by PID.
+            max_pid = std::cmp::max(max_pid, process.pid);
+
+            all_parent_pids_to_child_processes
+                .entry(parent_pid)
+                .or_default()
+                .push(process);
+        }
+
+        // We'll want to left-pad every printed PID with zeroes until it matches the length
+        // of the largest PID.
+        let max_num_pid_chars = format!("{max_pid}").len();
+
+        AllProcessesTree {
+            all_parent_pids_to_child_processes,
+            max_num_pid_chars,
+        }
+    }
 
-    AllProcessesTree {
-        all_parent_pids_to_child_processes,
-        max_num_pid_chars,
+    pub fn find(&self, pid: usize) -> Option<&Process> {
+        self.all_parent_pids_to_child_processes
+            .values()
+            .flatten()
+            .find(|process| process.pid == pid)
     }
-}
 
-impl AllProcessesTree {
     pub fn get_root(&self) -> &Process {
-        // The root process will always be the only child of a special parent PID.
+        // The root process will always be a child of a special parent PID. On Linux, it isn't
+        // the only one: kthreadd (PID 2), the parent of every kernel thread, has no parent
+        // either. Like the real pstree, we only show the tree under init, which has the
+        // lowest PID.
         let root_process_list: &Vec<Process> = self
             .all_parent_pids_to_child_processes
             .get(&ROOT_PARENT_PID)
             .expect("A root process with parent pid 0 must exist");
 
-        assert!(
-            root_process_list.len() == 1,
-            "There can only be one root process",
-        );
-
         &root_process_list[0]
     }
 }
@@ -84,10 +98,12 @@ impl Process {
     fn from_ps_line(line: &str) -> (Self, usize) {
         let re = PROCESS_LINE_REGEX
             // example line: "root               322     1   322 /usr/libexec/keybagd -t 15"
-            .get_or_init(|| Regex::new(r"^(\w+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(.*?)$").unwrap());
+            // Usernames can contain more than word characters (like "_www-data" or
+            // "jane.doe"), but never whitespace, and `ps` may pad them with leading spaces.
+            .get_or_init(|| Regex::new(r"^\s*(\S+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(.*?)$").unwrap());
         let captures = re
             .captures(line)
-            .expect(&format!("Failed to parse line from ps: {line}"));
+            .unwrap_or_else(|| panic!("Failed to parse line from ps: {line}"));
 
         let parent_pid = captures[3]
             .parse::<usize>()
@@ -108,3 +124,28 @@ impl Process {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ps_output() {
+        let tree = parse(
+            "USER               PID  PPID  PGID COMMAND\n\
+             root                 1     0     1 /sbin/launchd\n\
+             _www-data          322     1   322 /usr/sbin/httpd -D FOREGROUND\n\
+             jane.doe          4021     1  4021 /bin/zsh -l\n",
+        );
+
+        assert_eq!(tree.get_root().command, "/sbin/launchd");
+        let children = &tree.all_parent_pids_to_child_processes[&1];
+        assert_eq!(children[0].user, "_www-data");
+        assert_eq!(children[0].command, "/usr/sbin/httpd -D FOREGROUND");
+        assert_eq!(
+            (children[1].user.as_str(), children[1].pgid),
+            ("jane.doe", 4021)
+        );
+        assert_eq!(tree.max_num_pid_chars, 4);
+    }
+}
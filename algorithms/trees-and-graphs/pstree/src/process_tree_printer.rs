@@ -1,35 +1,106 @@
-use super::{Process, ROOT_PARENT_PID};
+use super::process_tree_filter::{Field, Filters};
+use super::Process;
 
 use crossterm::style::{style, Color, Stylize};
-use crossterm::terminal;
 use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
 
+/// How to print the tree, as chosen on the command line.
+pub struct PrintOptions {
+    /// Cut each line off after this many (visible) characters.
+    pub max_width: Option<usize>,
+    pub color: bool,
+    /// Show each process's full command line, rather than just the program's name.
+    pub show_arguments: bool,
+    /// Show each process's PGID after its PID.
+    pub show_pgids: bool,
+    pub line_drawing: LineDrawing,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineDrawing {
+    Unicode,
+    // For terminals (or fonts) that can't show the box-drawing characters
+    Ascii,
+}
+
+impl PrintOptions {
+    /// Add ANSI color codes to the text, unless color is turned off.
+    fn paint(&self, text: &str, color: Color) -> String {
+        if self.color {
+            style(text).with(color).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Paint the given ranges of the text white, to pick out what the filters matched, and the
+    /// rest of it in the given color (or not at all).
+    fn paint_matches(&self, text: &str, matches: &[Range<usize>], color: Option<Color>) -> String {
+        let paint_unmatched = |unmatched: &str| match color {
+            Some(color) if !unmatched.is_empty() => self.paint(unmatched, color),
+            _ => unmatched.to_string(),
+        };
+        let mut painted = String::new();
+        let mut end_of_last_match = 0;
+        for range in matches {
+            painted += &paint_unmatched(&text[end_of_last_match..range.start]);
+            painted += &self.paint(&text[range.clone()], Color::White);
+            end_of_last_match = range.end;
+        }
+        painted += &paint_unmatched(&text[end_of_last_match..]);
+        painted
+    }
+}
+
+/// One printed line of the tree, with the process it shows.
+pub struct Line {
+    pub pid: usize,
+    // Including any ANSI color codes, but not a newline
+    pub text: String,
+}
+
+/// Print the tree starting from the given root process, to the given writer.
 pub fn print(
+    out: &mut impl Write,
     parent_pids_to_child_processes: &HashMap<usize, Vec<Process>>,
+    root: &Process,
     max_num_pid_chars: usize,
-    filter_processes_by_text: Option<String>,
-) {
-    let terminal_width = terminal::size()
-        .expect("Failed to find terminal's dimensions")
-        .0 as usize;
-
-    if let Some(root) = &parent_pids_to_child_processes
-        .get(&ROOT_PARENT_PID)
-        .and_then(|root_process_list| root_process_list.get(0))
-    {
-        // Print our tree of processes -- either all of them, or all that remain after
-        // filtering. In principle we always want to do this, but it's nested in a conditional
-        // because we could have filtered out _everything_.
-        ProcessTreePrinter {
-            max_num_pid_chars,
-            terminal_width,
-            parent_pids_to_child_processes: &parent_pids_to_child_processes,
-            maybe_filter_text: filter_processes_by_text
-                .map(|s| s.to_lowercase())
-                .as_deref(),
-        }
-        .print_recursive(root, Vec::new());
+    filters: &Filters,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let lines = render(
+        parent_pids_to_child_processes,
+        root,
+        max_num_pid_chars,
+        filters,
+        options,
+    );
+    for line in lines {
+        writeln!(out, "{}", line.text)?;
     }
+    Ok(())
+}
+
+/// Render the tree starting from the given root process into lines, one per process, rather
+/// than printing it straight away. Watch mode uses this to redraw the tree in place.
+pub fn render(
+    parent_pids_to_child_processes: &HashMap<usize, Vec<Process>>,
+    root: &Process,
+    max_num_pid_chars: usize,
+    filters: &Filters,
+    options: &PrintOptions,
+) -> Vec<Line> {
+    let mut lines = Vec::new();
+    ProcessTreePrinter {
+        max_num_pid_chars,
+        options,
+        parent_pids_to_child_processes,
+        filters,
+    }
+    .render_recursive(&mut lines, root, Vec::new());
+    lines
 }
 
 // Any given Process's ChildPosition is that process's position relative to its own immediate
@@ -49,6 +120,7 @@ const COLORS: [Color; 3] = [Color::Yellow, Color::Red, Color::Cyan];
 /// R = Right, L = Left, T = Top, B = Bottom. So e.g. RL is a dash-like char that
 /// extends from left to right. DOUBLE_RL is a special case of two lines, like
 /// the equals sign.
+#[allow(clippy::upper_case_acronyms)]
 enum TreeChar {
     RL,
     DoubleRL,
@@ -59,31 +131,70 @@ enum TreeChar {
 }
 
 impl TreeChar {
-    fn to_char(&self) -> char {
+    fn to_char(&self, line_drawing: LineDrawing) -> char {
         use TreeChar::*;
-        match self {
-            RL => '─',
-            DoubleRL => '=', // could use ═, but it's less visually distinct from ─
-            RBL => '┬',
-            TRB => '├',
-            TB => '│',
-            TR => '└',
+        match (self, line_drawing) {
+            (RL, LineDrawing::Unicode) => '─',
+            (DoubleRL, _) => '=', // could use ═, but it's less visually distinct from ─
+            (RBL, LineDrawing::Unicode) => '┬',
+            (TRB, LineDrawing::Unicode) => '├',
+            (TB, LineDrawing::Unicode) => '│',
+            (TR, LineDrawing::Unicode) => '└',
+            // The same characters the real pstree uses for ASCII
+            (RL, LineDrawing::Ascii) => '-',
+            (RBL, LineDrawing::Ascii) => '+',
+            (TRB | TB, LineDrawing::Ascii) => '|',
+            (TR, LineDrawing::Ascii) => '`',
+        }
+    }
+}
+
+/// Cut the line off after `max_width` visible characters. ANSI escape sequences don't take up
+/// any room, so they're copied over without being counted (a highlight can add several of them
+/// anywhere in the line), and if we cut off a colored line we reset the colors where it ends.
+fn truncate_visible(line: &str, max_width: usize) -> String {
+    let mut truncated = String::new();
+    let mut num_visible_chars = 0;
+    let mut colored = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // A CSI sequence like "\x1b[38;5;11m" ends with a char from '@' to '~'
+            truncated.push(c);
+            colored = true;
+            for c in chars.by_ref() {
+                truncated.push(c);
+                if c != '[' && ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else if num_visible_chars == max_width {
+            if colored {
+                truncated.push_str("\x1b[0m");
+            }
+            break;
+        } else {
+            truncated.push(c);
+            num_visible_chars += 1;
         }
     }
+    truncated
 }
 
 /// Struct to capture arguments that we need when recursively printing lines, that do
 /// NOT differ between recursive calls.
-struct ProcessTreePrinter<'a, 'b> {
+struct ProcessTreePrinter<'a> {
     max_num_pid_chars: usize,
-    terminal_width: usize,
+    options: &'a PrintOptions,
     parent_pids_to_child_processes: &'a HashMap<usize, Vec<Process>>,
-    maybe_filter_text: Option<&'b str>,
+    // Used to highlight whatever the filters matched
+    filters: &'a Filters,
 }
 
-impl<'a, 'b> ProcessTreePrinter<'a, 'b> {
-    fn print_recursive(
+impl ProcessTreePrinter<'_> {
+    fn render_recursive(
         &self,
+        lines: &mut Vec<Line>,
         process: &Process,
         // Each process must know the 'child position' of ALL of its parents relative to their
         // parent, and its own 'child position' relative to its own parent. This vec is ordered
@@ -95,54 +206,61 @@ impl<'a, 'b> ProcessTreePrinter<'a, 'b> {
     ) {
         let Self {
             max_num_pid_chars,
-            terminal_width,
+            options,
             parent_pids_to_child_processes,
-            maybe_filter_text,
+            filters,
         } = self;
         let maybe_children = parent_pids_to_child_processes.get(&process.pid);
         let is_parent = maybe_children.is_some_and(|children| !children.is_empty());
 
-        let (tree_chars, num_visible_tree_chars) =
-            process.get_tree_chars(is_parent, &parent_to_self_child_positions);
+        let tree_chars =
+            process.get_tree_chars(is_parent, &parent_to_self_child_positions, options);
         let Process {
-            pid, user, command, ..
+            pid, pgid, user, ..
         } = process;
-        let formatted_pid = format!("{pid:0max_num_pid_chars$}");
-        // We add 3 to deal with whitespace we added between different pieces of text. Meanwhile,
-        // we assume text will be ASCII and so use len() instead of .chars().count().
-        let visible_content_length =
-            num_visible_tree_chars + 3 + formatted_pid.len() + user.len() + command.len();
-
-        let formatted_command = if let Some(filter_text) = maybe_filter_text {
-            if let Some(match_start_i) = command.to_lowercase().find(filter_text) {
-                let match_end_i = match_start_i + filter_text.len();
-                &format!(
-                    "{}{}{}",
-                    &command[..match_start_i],
-                    &command[match_start_i..match_end_i].white(),
-                    &command[match_end_i..]
-                )
-            } else {
-                // Annoying to have to use this `else` case twice -- very soon Rust will
-                // support 'if let chaining', which would clean this up
-                command
-            }
+        let command = if options.show_arguments {
+            &process.command
         } else {
-            command
+            process.name()
         };
-
-        let process_line = format!(
-            "{tree_chars} {} {} {formatted_command}",
-            formatted_pid.blue(),
-            style(&user).with(Color::Magenta)
+        // A filter on PID or PGID matches the whole number, not the zeroes we pad it with, so
+        // we highlight all of it.
+        let paint_id = |id: usize, field, color| {
+            let highlighted = !filters.highlights(field, &id.to_string()).is_empty();
+            options.paint(
+                &format!("{id:0max_num_pid_chars$}"),
+                if highlighted { Color::White } else { color },
+            )
+        };
+        let formatted_pid = if options.show_pgids {
+            format!(
+                "{} {}",
+                paint_id(*pid, Field::Pid, Color::Blue),
+                paint_id(*pgid, Field::Pgid, Color::DarkGrey)
+            )
+        } else {
+            paint_id(*pid, Field::Pid, Color::Blue)
+        };
+        let formatted_user = options.paint_matches(
+            user,
+            &filters.highlights(Field::User, user),
+            Some(Color::Magenta),
         );
-        // We have to calculate how many ansi color code characters are present, because we add
-        // a variable amount of them to our tree chars.
-        let num_ansi_color_chars = process_line.chars().count() - visible_content_length;
-        let num_chars_to_print = terminal_width + num_ansi_color_chars;
-        println!("{process_line:.num_chars_to_print$}");
+        let formatted_command =
+            options.paint_matches(command, &filters.highlights(Field::Command, command), None);
+
+        let process_line =
+            format!("{tree_chars} {formatted_pid} {formatted_user} {formatted_command}");
+        let text = match options.max_width {
+            Some(max_width) => truncate_visible(&process_line, max_width),
+            None => process_line,
+        };
+        lines.push(Line {
+            pid: process.pid,
+            text,
+        });
 
-        // recursively print all children of the current process
+        // recursively render all children of the current process
         if let Some(children) = maybe_children {
             for (i, child_process) in children.iter().enumerate() {
                 // The child needs to know whether it is itself a 'middle' or 'last' child;
@@ -154,19 +272,18 @@ impl<'a, 'b> ProcessTreePrinter<'a, 'b> {
                 };
                 let mut new_parent_to_self_child_positions = parent_to_self_child_positions.clone();
                 new_parent_to_self_child_positions.push(child_position);
-                self.print_recursive(child_process, new_parent_to_self_child_positions);
+                self.render_recursive(lines, child_process, new_parent_to_self_child_positions);
             }
         }
     }
 }
 
 impl Process {
-    /// Return a tuple with a string of 'tree chars', and the number of visible (non-ANSI-color-code)
-    /// characters in that string (where whitespace counts as 'visible'). This function describes just
-    /// a single line -- a row, or horizontal slice -- of the larger tree we'll print. Setting aside
-    /// colors, here's an example output:
-    /// (" │ │   └─┬=", 11)
-    /// In total, there are eleven visible characters there. We can call the first eight "child
+    /// Return a string of 'tree chars', colored unless color is turned off. This function describes
+    /// just a single line -- a row, or horizontal slice -- of the larger tree we'll print. Setting
+    /// aside colors, here's an example output:
+    /// " │ │   └─┬="
+    /// In total, there are eleven visible characters there (whitespace counts as 'visible'). We can call the first eight "child
     /// positional" chars: they form four pairs of characters, where each pair describes the position
     /// of a child relative to its parent. (That position may be 'last child' or 'middle' aka 'non-
     /// last.) Since there are four pairs of positional characters, we're dealing with four parents:
@@ -181,6 +298,7 @@ impl Process {
     ///   be drawn to a following sibling.
     /// - The final " └" shows that PC is the LAST child of P3. If there were a sibling of PC to
     ///   display below, we'd have used " ├" instead.
+    ///
     /// After those "child positional" chars come three final characters, which describe PC (and
     /// indicate whether it has any children itself).
     /// - The first of these final chars is always '─'.
@@ -189,29 +307,26 @@ impl Process {
     fn get_tree_chars(
         &self,
         is_parent: bool,
-        parent_to_self_child_positions: &Vec<ChildPosition>,
-    ) -> (String, usize) {
+        parent_to_self_child_positions: &[ChildPosition],
+        options: &PrintOptions,
+    ) -> String {
         let mut colors_i = 0; // the tree will cycle through multiple colors based on this index
-        let mut num_visible_chars: usize = 0;
         let mut s = String::new();
 
         // First off, add any 'child positional' characters to our empty starter string.
         for (position_i, position) in parent_to_self_child_positions.iter().enumerate() {
             let child_is_current_process = position_i == parent_to_self_child_positions.len() - 1;
             let position_char = match (position, child_is_current_process) {
-                (ChildPosition::MiddleChild, false) => TreeChar::TB.to_char(),
+                (ChildPosition::MiddleChild, false) => TreeChar::TB.to_char(options.line_drawing),
                 (ChildPosition::LastChild, false) => ' ',
-                (ChildPosition::MiddleChild, true) => TreeChar::TRB.to_char(),
-                (ChildPosition::LastChild, true) => TreeChar::TR.to_char(),
+                (ChildPosition::MiddleChild, true) => TreeChar::TRB.to_char(options.line_drawing),
+                (ChildPosition::LastChild, true) => TreeChar::TR.to_char(options.line_drawing),
             };
             let mut unstyled = String::new();
             unstyled.push(' ');
             unstyled.push(position_char);
-            let styled = style(unstyled)
-                .with(COLORS[colors_i % COLORS.len()])
-                .to_string();
-            s.extend(styled.chars());
-            num_visible_chars += 2;
+            let styled = options.paint(&unstyled, COLORS[colors_i % COLORS.len()]);
+            s.push_str(&styled);
 
             // don't do an extra color change when we're stopping iteration; we
             // want the final characters to match the color we were just using
@@ -232,14 +347,135 @@ impl Process {
             TreeChar::RL
         };
         let final_chars = [TreeChar::RL, branch_to_children_tree_char, last_tree_char]
-            .map(|tc| tc.to_char())
+            .map(|tc| tc.to_char(options.line_drawing))
             .iter()
             .collect::<String>();
-        num_visible_chars += 3;
-        let final_chars_styled = style(final_chars)
-            .with(COLORS[colors_i % COLORS.len()])
-            .to_string();
-        s.extend(final_chars_styled.chars());
-        (s, num_visible_chars)
+        let final_chars_styled = options.paint(&final_chars, COLORS[colors_i % COLORS.len()]);
+        s.push_str(&final_chars_styled);
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_tree_parser::AllProcessesTree;
+    use crate::{parse_args, select_processes};
+    use std::fs;
+    use std::path::Path;
+
+    // A recorded tree from a Mac, with a few logins to filter for
+    const SNAPSHOT: &str = include_str!("../testdata/snapshot.json");
+
+    /// Print the tree from the snapshot, as if run with the given command-line arguments.
+    fn render(args: &[&str], max_width: Option<usize>) -> String {
+        // Colors are part of what we're checking, so don't let NO_COLOR turn them off
+        crossterm::style::force_color_output(true);
+
+        let options = parse_args(args.iter().map(|arg| arg.to_string()));
+        let all_processes_tree = AllProcessesTree::from_json(SNAPSHOT).unwrap();
+        let all_processes_root = match options.root_pid {
+            Some(pid) => all_processes_tree.find(pid).unwrap(),
+            None => all_processes_tree.get_root(),
+        };
+        let Some((parent_pids_to_child_processes, root)) =
+            select_processes(&all_processes_tree, all_processes_root, &options)
+        else {
+            return String::new();
+        };
+
+        let mut out = Vec::new();
+        print(
+            &mut out,
+            &parent_pids_to_child_processes,
+            &root,
+            all_processes_tree.max_num_pid_chars,
+            &options.filters,
+            &options.print_options(max_width),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Compare some output with what's saved in testdata/golden. After a change that's meant
+    /// to alter the output, run the tests with UPDATE_GOLDEN=1 to save the new output instead,
+    /// and check the difference with `git diff` (or `cat` them, to see the colors).
+    fn assert_golden(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/golden")
+            .join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::write(&path, actual).unwrap();
+        }
+        let expected = fs::read_to_string(&path).unwrap();
+        assert!(
+            actual == expected,
+            "Output differs from {}:\n{actual}",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn full_tree() {
+        assert_golden("full_tree.txt", &render(&[], None));
+    }
+
+    #[test]
+    fn cut_off_at_terminal_width() {
+        assert_golden("full_tree_40_columns.txt", &render(&[], Some(40)));
+    }
+
+    #[test]
+    fn filtered_and_cut_off_at_terminal_width() {
+        // Every highlight adds escape sequences, and none of them may count towards the width
+        let output = render(&["-a", "-r", "--or", "login", "Log", "/"], Some(60));
+        assert_golden("regex_login_or_slash_60_columns.txt", &output);
+    }
+
+    #[test]
+    fn filtered() {
+        assert_golden("filter_login.txt", &render(&["-a", "LOGIN"], None));
+    }
+
+    #[test]
+    fn subtree_of_one_process() {
+        assert_golden("pid_512.txt", &render(&["-p", "512"], None));
+    }
+
+    #[test]
+    fn one_users_processes() {
+        assert_golden("user_root.txt", &render(&["-u", "root"], None));
+    }
+
+    #[test]
+    fn filtered_by_text_and_user() {
+        assert_golden(
+            "filter_login_user_root.txt",
+            &render(&["-u", "root", "-a", "login"], None),
+        );
+    }
+
+    #[test]
+    fn user_still_required_with_or() {
+        let output = render(&["-u", "root", "--or", "-a", "login", "zsh"], None);
+        assert_golden("user_root_login_or_zsh.txt", &output);
+    }
+
+    #[test]
+    fn plain_ascii_with_pgids() {
+        let output = render(&["-A", "--no-color", "-a", "-g"], None);
+        assert_golden("ascii_no_color_pgids.txt", &output);
+    }
+
+    #[test]
+    fn regex_filters_combined_with_or() {
+        let output = render(&["-a", "-r", "--or", "^login", "pid=^22"], None);
+        assert_golden("regex_login_or_pid_22.txt", &output);
+    }
+
+    #[test]
+    fn inverted_filters() {
+        let output = render(&["-v", "user=root", "-v", "user=jane.doe"], None);
+        assert_golden("not_root_or_jane.txt", &output);
     }
 }
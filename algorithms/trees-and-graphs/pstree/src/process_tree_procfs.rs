@@ -0,0 +1,255 @@
+use super::process_tree_parser::AllProcessesTree;
+use super::Process;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// The error for a process that no longer exists. We're only ever on Linux here, where it's 3.
+const ESRCH: i32 = 3;
+
+/// Build the tree by reading Linux's /proc directly, rather than running `ps` and parsing its
+/// output. Every process has a directory there named after its PID, and we read three files
+/// from each: `stat` for the parent PID and PGID, `status` for the user ID, and `cmdline` for
+/// the full command. The user ID is turned into a name with `/etc/passwd`, just like `ps` does.
+pub fn read_proc_and_parse() -> io::Result<AllProcessesTree> {
+    // A missing or unreadable passwd file isn't fatal; we'll just show user IDs as numbers.
+    let usernames = fs::read_to_string("/etc/passwd")
+        .map(|passwd| parse_passwd(&passwd))
+        .unwrap_or_default();
+    read(Path::new("/proc"), &usernames)
+}
+
+/// Read every process in the given procfs directory. Taking the directory as an argument
+/// (rather than always using /proc) lets us test against a fake one.
+fn read(proc_dir: &Path, usernames: &HashMap<u32, String>) -> io::Result<AllProcessesTree> {
+    // Besides a directory per process, /proc holds plenty of other things, like `cpuinfo` and
+    // `self`. Only the directories named with a number are processes.
+    let mut pids: Vec<usize> = fs::read_dir(proc_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    // `ps` sorts by PID, and so do we, so that siblings come out in the same order.
+    pids.sort_unstable();
+
+    let mut processes = Vec::with_capacity(pids.len());
+    for pid in pids {
+        match read_process(&proc_dir.join(pid.to_string()), pid, usernames) {
+            Ok(process_and_parent_pid) => processes.push(process_and_parent_pid),
+            // Processes come and go while we're reading. If one has exited since we listed the
+            // directory, its files will have gone with it, and we simply leave it out.
+            Err(e) if has_exited(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(AllProcessesTree::from_processes(processes))
+}
+
+/// Whether reading a process's files failed because it exited partway through. Usually they're
+/// just gone, but reading one that's still open as the process exits gives ESRCH instead.
+fn has_exited(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::NotFound || error.raw_os_error() == Some(ESRCH)
+}
+
+/// Return a tuple of the process in the given /proc/<pid> directory, and its parent PID.
+fn read_process(
+    process_dir: &Path,
+    pid: usize,
+    usernames: &HashMap<u32, String>,
+) -> io::Result<(Process, usize)> {
+    let invalid = |file: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse {}", process_dir.join(file).display()),
+        )
+    };
+
+    // example stat: "322 (keybagd) S 1 322 322 0 -1 ..." and on for another forty-odd fields.
+    // The name in parentheses is the executable's, which may itself contain spaces and
+    // parentheses, so we look for the fields we want after the _last_ closing parenthesis.
+    let stat = fs::read_to_string(process_dir.join("stat"))?;
+    let (name, after_name) = stat
+        .split_once(" (")
+        .and_then(|(_, rest)| rest.rsplit_once(") "))
+        .ok_or_else(|| invalid("stat"))?;
+    // The fields after the name are state, parent PID and PGID.
+    let mut fields = after_name.split_whitespace().skip(1);
+    let mut next_number = || -> Option<usize> { fields.next()?.parse().ok() };
+    let (parent_pid, pgid) = next_number()
+        .zip(next_number())
+        .ok_or_else(|| invalid("stat"))?;
+
+    // example line from status: "Uid:	501	501	501	501". The four IDs are the real,
+    // effective, saved and filesystem user IDs; `ps -o user` shows the effective one.
+    let status = fs::read_to_string(process_dir.join("status"))?;
+    let uid: u32 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|uids| uids.split_whitespace().nth(1)?.parse().ok())
+        .ok_or_else(|| invalid("status"))?;
+    let user = usernames
+        .get(&uid)
+        .cloned()
+        .unwrap_or_else(|| uid.to_string());
+
+    // cmdline holds each argument followed by a NUL byte. Kernel threads, and processes that
+    // have exited but not yet been reaped, have no arguments at all; for those `ps` shows the
+    // name from stat in square brackets, so we do the same.
+    let cmdline = fs::read(process_dir.join("cmdline"))?;
+    let args: Vec<_> = cmdline
+        .split(|&byte| byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect();
+    let command = if args.is_empty() {
+        format!("[{name}]")
+    } else {
+        args.join(" ")
+    };
+
+    Ok((
+        Process {
+            pid,
+            pgid,
+            user,
+            command,
+        },
+        parent_pid,
+    ))
+}
+
+/// Map user IDs to names, given the contents of an /etc/passwd file. Each line looks like
+/// "root:x:0:0:root:/root:/bin/bash", with the name first and the user ID third.
+fn parse_passwd(passwd: &str) -> HashMap<u32, String> {
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A directory laid out like /proc, holding only the files we read, which is deleted
+    /// again when dropped.
+    struct FakeProcfs {
+        dir: PathBuf,
+    }
+
+    impl FakeProcfs {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("color-pstree-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("sys")).unwrap();
+            fs::write(dir.join("cpuinfo"), "not a process").unwrap();
+            FakeProcfs { dir }
+        }
+
+        fn add(
+            &self,
+            pid: usize,
+            stat_name: &str,
+            ppid: usize,
+            pgid: usize,
+            uid: u32,
+            cmdline: &str,
+        ) {
+            let process_dir = self.dir.join(pid.to_string());
+            fs::create_dir(&process_dir).unwrap();
+            fs::write(
+                process_dir.join("stat"),
+                format!("{pid} ({stat_name}) S {ppid} {pgid} {pgid} 0 -1 4194560 0 0\n"),
+            )
+            .unwrap();
+            fs::write(
+                process_dir.join("status"),
+                format!("Name:\t{stat_name}\nState:\tS (sleeping)\nUid:\t0\t{uid}\t{uid}\t{uid}\n"),
+            )
+            .unwrap();
+            fs::write(process_dir.join("cmdline"), cmdline).unwrap();
+        }
+    }
+
+    impl Drop for FakeProcfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn usernames() -> HashMap<u32, String> {
+        parse_passwd("root:x:0:0:root:/root:/bin/bash\n_www-data:*:70:70::/:/usr/bin/false\n")
+    }
+
+    #[test]
+    fn reads_processes() {
+        let procfs = FakeProcfs::new("reads-processes");
+        procfs.add(1, "init", 0, 1, 0, "/sbin/init\0splash\0");
+        procfs.add(12, "sshd", 1, 12, 0, "sshd: /usr/sbin/sshd -D\0");
+        procfs.add(130, "httpd", 12, 130, 70, "/usr/sbin/httpd\0-k\0start\0");
+        procfs.add(9, "bash", 1, 9, 1000, "-bash\0");
+
+        let tree = read(&procfs.dir, &usernames()).unwrap();
+
+        let root = tree.get_root();
+        assert_eq!((root.pid, root.user.as_str()), (1, "root"));
+        assert_eq!(root.command, "/sbin/init splash");
+        let children = &tree.all_parent_pids_to_child_processes[&1];
+        // Sorted by PID, even though 12 was created first
+        assert_eq!(children.iter().map(|p| p.pid).collect::<Vec<_>>(), [9, 12]);
+        // An unknown user ID is shown as a number
+        assert_eq!(children[0].user, "1000");
+        let httpd = &tree.all_parent_pids_to_child_processes[&12][0];
+        assert_eq!(httpd.user, "_www-data");
+        assert_eq!(httpd.command, "/usr/sbin/httpd -k start");
+        assert_eq!(tree.max_num_pid_chars, 3);
+    }
+
+    #[test]
+    fn tricky_names_and_kernel_threads() {
+        let procfs = FakeProcfs::new("tricky-names");
+        procfs.add(1, "init", 0, 1, 0, "/sbin/init\0");
+        procfs.add(2, "kthreadd", 0, 0, 0, "");
+        procfs.add(3, "kworker/0:0-events", 2, 0, 0, "");
+        procfs.add(40, "weird) (name", 1, 40, 0, "");
+
+        let tree = read(&procfs.dir, &usernames()).unwrap();
+
+        // kthreadd has no parent either, but the root is init
+        assert_eq!(tree.get_root().pid, 1);
+        assert_eq!(
+            tree.all_parent_pids_to_child_processes[&2][0].command,
+            "[kworker/0:0-events]"
+        );
+        let weird = &tree.all_parent_pids_to_child_processes[&1][0];
+        assert_eq!((weird.pid, weird.pgid), (40, 40));
+        assert_eq!(weird.command, "[weird) (name]");
+    }
+
+    #[test]
+    fn malformed_stat() {
+        let procfs = FakeProcfs::new("malformed-stat");
+        procfs.add(1, "init", 0, 1, 0, "/sbin/init\0");
+        fs::write(procfs.dir.join("1").join("stat"), "1 init S").unwrap();
+
+        let Err(error) = read(&procfs.dir, &usernames()) else {
+            panic!("A stat without parentheses shouldn't parse");
+        };
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn exited_processes() {
+        assert!(has_exited(&io::ErrorKind::NotFound.into()));
+        assert!(has_exited(&io::Error::from_raw_os_error(ESRCH)));
+        assert!(!has_exited(&io::ErrorKind::PermissionDenied.into()));
+    }
+}
@@ -0,0 +1,137 @@
+use super::process_tree_parser::AllProcessesTree;
+use super::Process;
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A snapshot is a JSON object with a flat list of processes, each with its parent PID:
+///
+/// ```json
+/// {
+///   "processes": [
+///     { "pid": 1, "ppid": 0, "pgid": 1, "user": "root", "command": "/sbin/launchd" },
+///     ...
+///   ]
+/// }
+/// ```
+///
+/// That's easier to read (and to write by hand) than the map of parent PIDs to children that
+/// we keep in memory, and we can rebuild that map from it exactly.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    processes: Vec<SnapshotProcess>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotProcess {
+    pid: usize,
+    ppid: usize,
+    pgid: usize,
+    user: String,
+    command: String,
+}
+
+impl AllProcessesTree {
+    pub fn to_json(&self) -> String {
+        let mut processes: Vec<SnapshotProcess> = self
+            .all_parent_pids_to_child_processes
+            .iter()
+            .flat_map(|(&ppid, children)| {
+                children.iter().map(move |process| SnapshotProcess {
+                    pid: process.pid,
+                    ppid,
+                    pgid: process.pgid,
+                    user: process.user.clone(),
+                    command: process.command.clone(),
+                })
+            })
+            .collect();
+        // The map has no order of its own, so sort by PID, just like `ps` does.
+        processes.sort_unstable_by_key(|process| process.pid);
+
+        serde_json::to_string_pretty(&Snapshot { processes }).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let mut snapshot: Snapshot = serde_json::from_str(json)?;
+        // `from_processes` needs them in PID order, and a hand-written snapshot might not be.
+        snapshot
+            .processes
+            .sort_unstable_by_key(|process| process.pid);
+
+        Ok(AllProcessesTree::from_processes(
+            snapshot.processes.into_iter().map(|process| {
+                (
+                    Process {
+                        pid: process.pid,
+                        pgid: process.pgid,
+                        user: process.user,
+                        command: process.command,
+                    },
+                    process.ppid,
+                )
+            }),
+        ))
+    }
+}
+
+pub fn save(all_processes_tree: &AllProcessesTree, path: &Path) -> io::Result<()> {
+    fs::write(path, all_processes_tree.to_json() + "\n")
+}
+
+pub fn load(path: &Path) -> io::Result<AllProcessesTree> {
+    let json = fs::read_to_string(path)?;
+    AllProcessesTree::from_json(&json).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid snapshot {}: {e}", path.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let json = r#"{
+  "processes": [
+    {
+      "pid": 1,
+      "ppid": 0,
+      "pgid": 1,
+      "user": "root",
+      "command": "/sbin/launchd"
+    },
+    {
+      "pid": 88,
+      "ppid": 1,
+      "pgid": 88,
+      "user": "_www-data",
+      "command": "/usr/sbin/httpd -D FOREGROUND"
+    },
+    {
+      "pid": 102,
+      "ppid": 88,
+      "pgid": 88,
+      "user": "_www-data",
+      "command": "/usr/sbin/httpd -D FOREGROUND"
+    }
+  ]
+}"#;
+        let tree = AllProcessesTree::from_json(json).unwrap();
+
+        assert_eq!(tree.get_root().command, "/sbin/launchd");
+        assert_eq!(tree.all_parent_pids_to_child_processes[&88][0].pid, 102);
+        assert_eq!(tree.max_num_pid_chars, 3);
+        assert_eq!(tree.to_json(), json);
+    }
+
+    #[test]
+    fn invalid_snapshot() {
+        assert!(AllProcessesTree::from_json(r#"{"processes": [{"pid": 1}]}"#).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use super::process_tree_parser::{self, AllProcessesTree};
+use super::process_tree_procfs;
+use super::process_tree_snapshot;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Somewhere to get the tree of processes from. Usually that's the running system, but it can
+/// also be a snapshot of one that we saved earlier, which is what lets us test everything
+/// downstream without depending on whatever happens to be running at the time.
+pub trait ProcessSource {
+    fn load(&self) -> io::Result<AllProcessesTree>;
+}
+
+/// Run `ps` and parse its output.
+pub struct Ps;
+
+impl ProcessSource for Ps {
+    fn load(&self) -> io::Result<AllProcessesTree> {
+        process_tree_parser::execute_ps_and_parse()
+    }
+}
+
+/// Read Linux's /proc directly.
+pub struct Procfs;
+
+impl ProcessSource for Procfs {
+    fn load(&self) -> io::Result<AllProcessesTree> {
+        process_tree_procfs::read_proc_and_parse()
+    }
+}
+
+/// Whichever of the above works on this system: /proc where there is one (on Linux), and
+/// otherwise `ps`. We also fall back to `ps` if /proc turns out to be unreadable, as it can be
+/// in some sandboxes.
+pub struct Live;
+
+impl ProcessSource for Live {
+    fn load(&self) -> io::Result<AllProcessesTree> {
+        if Path::new("/proc/self/stat").exists() {
+            match Procfs.load() {
+                Ok(all_processes_tree) => return Ok(all_processes_tree),
+                Err(e) => eprintln!("Failed to read /proc, falling back to ps: {e}"),
+            }
+        }
+        Ps.load()
+    }
+}
+
+/// A snapshot file, as written by `process_tree_snapshot::save`.
+pub struct SnapshotFile(pub PathBuf);
+
+impl ProcessSource for SnapshotFile {
+    fn load(&self) -> io::Result<AllProcessesTree> {
+        process_tree_snapshot::load(&self.0)
+    }
+}
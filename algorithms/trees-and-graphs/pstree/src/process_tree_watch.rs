@@ -0,0 +1,537 @@
+use super::process_tree_parser::AllProcessesTree;
+use super::process_tree_printer::{self, Line};
+use super::process_tree_source::ProcessSource;
+use super::{select_processes, Options, Process, ROOT_PARENT_PID};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{style, Color, Stylize};
+use crossterm::terminal::{
+    self, Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// How many updates a new process stays highlighted for, and an exited one stays in the tree.
+const UPDATES_TO_SHOW_CHANGES: u32 = 3;
+
+/// Redraw the tree every `interval`, in the terminal's alternate screen (the way `top` and
+/// `less` take over the whole terminal, and give it back as it was when they're done), until
+/// the user quits.
+pub fn run(
+    source: &dyn ProcessSource,
+    first_tree: AllProcessesTree,
+    options: &Options,
+    interval: Duration,
+) -> io::Result<()> {
+    let mut out = io::stdout();
+    let _screen = AlternateScreen::enter(&mut out)?;
+
+    let mut watch = Watch::default();
+    let mut tree = watch.update(first_tree);
+    let mut next_update = Instant::now() + interval;
+    loop {
+        watch.render(&tree, options);
+        let (_, rows) = terminal::size()?;
+        watch.draw(&mut out, rows as usize, interval, options.color)?;
+
+        // Wait for a key, or until it's time to update. Anything else that happens, like the
+        // terminal being resized, just means drawing again.
+        let timeout = next_update.saturating_duration_since(Instant::now());
+        if !event::poll(timeout)? {
+            tree = watch.update(source.load()?);
+            next_update = Instant::now() + interval;
+        } else if let Event::Key(key) = event::read()? {
+            // Leave one row for the status line
+            let page_height = (rows as usize).saturating_sub(1).max(1);
+            if key.kind != KeyEventKind::Release && watch.handle_key(key, page_height) == Quit {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Switches the terminal over to the alternate screen, in raw mode so that we get keys as
+/// they're pressed, and switches it back again when dropped. That includes when we panic,
+/// or return early with an error, as long as it's not from `enter` itself.
+struct AlternateScreen;
+
+impl AlternateScreen {
+    fn enter(out: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // Rather than wrap lines that are too long onto the next row, which would push the rest
+        // of the tree down, the terminal cuts them off at its edge.
+        execute!(out, EnterAlternateScreen, Hide, DisableLineWrap)?;
+        Ok(AlternateScreen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), EnableLineWrap, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Something that happened to a process between two updates.
+enum Change {
+    Spawned,
+    // We hold on to exited processes, so that we can keep showing them for a while.
+    Exited { process: Process, parent_pid: usize },
+}
+
+struct RecentChange {
+    change: Change,
+    updates_left: u32,
+}
+
+#[derive(PartialEq, Debug)]
+enum KeyAction {
+    Continue,
+    Quit,
+}
+use KeyAction::*;
+
+/// Everything watch mode keeps track of between one drawing of the tree and the next.
+#[derive(Default)]
+struct Watch {
+    // Every process as of the last update, with its parent's PID. This is None until the first
+    // update, because at that point every process is new, but it would be no use to highlight
+    // them all.
+    last_seen: Option<HashMap<usize, (Process, usize)>>,
+    recent_changes: HashMap<usize, RecentChange>,
+    // Processes whose children the user has hidden
+    collapsed: HashSet<usize>,
+    lines: Vec<Line>,
+    // The index of the line the user has selected with the arrow keys, and its process. After
+    // an update the process may have moved to a different line, and we follow it there.
+    selected: usize,
+    selected_pid: Option<usize>,
+    // The index of the line at the top of the screen
+    scrolled_to: usize,
+}
+
+impl Watch {
+    /// Compare a newly loaded tree with the last one, to find what's been spawned and what's
+    /// exited since. Returns the new tree, with any recently exited processes put back in.
+    fn update(&mut self, tree: AllProcessesTree) -> AllProcessesTree {
+        let current: HashMap<usize, (Process, usize)> = tree
+            .all_parent_pids_to_child_processes
+            .iter()
+            .flat_map(|(&parent_pid, children)| {
+                children
+                    .iter()
+                    .map(move |child| (child.pid, (child.clone(), parent_pid)))
+            })
+            .collect();
+
+        // Changes we already knew about have now been shown for one more update.
+        self.recent_changes.retain(|_, recent| {
+            recent.updates_left -= 1;
+            recent.updates_left > 0
+        });
+        if let Some(last_seen) = &self.last_seen {
+            let spawned = current.keys().filter(|pid| !last_seen.contains_key(pid));
+            for &pid in spawned {
+                self.recent_changes
+                    .insert(pid, RecentChange::new(Change::Spawned));
+            }
+            let exited = last_seen
+                .iter()
+                .filter(|(pid, _)| !current.contains_key(pid));
+            for (&pid, (process, parent_pid)) in exited {
+                let change = Change::Exited {
+                    process: process.clone(),
+                    parent_pid: *parent_pid,
+                };
+                self.recent_changes.insert(pid, RecentChange::new(change));
+            }
+        }
+
+        let mut processes: Vec<(Process, usize)> = current.values().cloned().collect();
+        for (pid, recent) in &self.recent_changes {
+            if let Change::Exited {
+                process,
+                parent_pid,
+            } = &recent.change
+            {
+                // A new process might have been given the same PID already.
+                if !current.contains_key(pid) {
+                    processes.push((process.clone(), *parent_pid));
+                }
+            }
+        }
+        // Siblings are printed in the order they're given, which is by PID everywhere else.
+        processes.sort_unstable_by_key(|(process, _)| process.pid);
+        self.last_seen = Some(current);
+        AllProcessesTree::from_processes(processes)
+    }
+
+    /// Render the tree into lines, leaving out the children of collapsed processes, and
+    /// coloring the processes that have changed.
+    fn render(&mut self, tree: &AllProcessesTree, options: &Options) {
+        // The root may be gone by now, if it was given with -p and has exited.
+        let all_processes_root = match options.root_pid {
+            Some(pid) => tree.find(pid),
+            None => tree
+                .all_parent_pids_to_child_processes
+                .get(&ROOT_PARENT_PID)
+                .and_then(|roots| roots.first()),
+        };
+        let Some((mut parent_pids_to_child_processes, root)) = all_processes_root
+            .and_then(|all_processes_root| select_processes(tree, all_processes_root, options))
+        else {
+            self.lines.clear();
+            self.follow_selection();
+            return;
+        };
+
+        let num_hidden = self.collapse(&mut parent_pids_to_child_processes);
+        // Lines aren't cut off at the terminal's width here; the terminal does that itself.
+        let lines = process_tree_printer::render(
+            &parent_pids_to_child_processes,
+            &root,
+            tree.max_num_pid_chars,
+            &options.filters,
+            &options.print_options(None),
+        );
+
+        self.lines = lines;
+        for line in &mut self.lines {
+            if let Some(num_hidden) = num_hidden.get(&line.pid) {
+                line.text += &format!(" [+{num_hidden}]");
+            }
+            // The background shows through the colors the printer uses, which only change
+            // the foreground.
+            let background = match self.recent_changes.get(&line.pid).map(|r| &r.change) {
+                Some(Change::Spawned) => Some(Color::DarkGreen),
+                Some(Change::Exited { .. }) => Some(Color::DarkRed),
+                None => None,
+            };
+            if let Some(background) = background.filter(|_| options.color) {
+                line.text = style(&line.text).on(background).to_string();
+            }
+        }
+        self.follow_selection();
+    }
+
+    /// Take the children of collapsed processes out of the tree. Returns how many processes
+    /// were hidden under each collapsed process that had any.
+    fn collapse(
+        &self,
+        parent_pids_to_child_processes: &mut HashMap<usize, Vec<Process>>,
+    ) -> HashMap<usize, usize> {
+        fn count_descendants(pid: usize, tree: &HashMap<usize, Vec<Process>>) -> usize {
+            tree.get(&pid).map_or(0, |children| {
+                children
+                    .iter()
+                    .map(|child| 1 + count_descendants(child.pid, tree))
+                    .sum()
+            })
+        }
+
+        // Count everything before taking anything out, since one collapsed process can be
+        // under another.
+        let num_hidden: HashMap<usize, usize> = self
+            .collapsed
+            .iter()
+            .map(|&pid| (pid, count_descendants(pid, parent_pids_to_child_processes)))
+            .filter(|&(_, num_hidden)| num_hidden > 0)
+            .collect();
+        for pid in num_hidden.keys() {
+            parent_pids_to_child_processes.remove(pid);
+        }
+        num_hidden
+    }
+
+    /// Keep the selection on the same process if it's still there, and otherwise on the same
+    /// line, as near as we can.
+    fn follow_selection(&mut self) {
+        let followed = self
+            .selected_pid
+            .and_then(|pid| self.lines.iter().position(|line| line.pid == pid));
+        self.select(followed.unwrap_or(self.selected));
+    }
+
+    fn select(&mut self, i: usize) {
+        self.selected = i.min(self.lines.len().saturating_sub(1));
+        self.selected_pid = self.lines.get(self.selected).map(|line| line.pid);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, page_height: usize) -> KeyAction {
+        let selected = self.selected;
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Quit,
+            KeyCode::Char('q') | KeyCode::Esc => return Quit,
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::PageUp => self.select(selected.saturating_sub(page_height)),
+            KeyCode::PageDown => self.select(selected + page_height),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::Left | KeyCode::Char('h') => self.set_collapsed(true),
+            KeyCode::Right | KeyCode::Char('l') => self.set_collapsed(false),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let collapsed = self
+                    .selected_pid
+                    .is_some_and(|pid| self.collapsed.contains(&pid));
+                self.set_collapsed(!collapsed);
+            }
+            _ => {}
+        }
+        Continue
+    }
+
+    fn set_collapsed(&mut self, collapsed: bool) {
+        if let Some(pid) = self.selected_pid {
+            if collapsed {
+                self.collapsed.insert(pid);
+            } else {
+                self.collapsed.remove(&pid);
+            }
+        }
+    }
+
+    /// Draw a status line, and as many lines of the tree as fit below it, scrolling so that the
+    /// selected line is on screen.
+    fn draw(
+        &mut self,
+        out: &mut impl Write,
+        rows: usize,
+        interval: Duration,
+        color: bool,
+    ) -> io::Result<()> {
+        let height = rows.saturating_sub(1);
+        if self.selected < self.scrolled_to {
+            self.scrolled_to = self.selected;
+        } else if self.selected >= self.scrolled_to + height {
+            self.scrolled_to = self.selected + 1 - height;
+        }
+        // Don't leave empty rows at the bottom if there are lines above that could fill them.
+        self.scrolled_to = self
+            .scrolled_to
+            .min(self.lines.len().saturating_sub(height));
+
+        let (num_spawned, num_exited) =
+            self.recent_changes
+                .values()
+                .fold((0, 0), |(spawned, exited), recent| match recent.change {
+                    Change::Spawned => (spawned + 1, exited),
+                    Change::Exited { .. } => (spawned, exited + 1),
+                });
+        let status = format!(
+            "Every {interval:?}: {} processes, {num_spawned} new, {num_exited} exited. \
+             ↑/↓ to move, space to collapse, q to quit",
+            self.lines.len()
+        );
+        // Each row is cleared after drawing it, rather than clearing the whole screen first,
+        // which would flicker.
+        queue!(out, MoveTo(0, 0))?;
+        write!(
+            out,
+            "{}",
+            if color {
+                status.bold().to_string()
+            } else {
+                status
+            }
+        )?;
+        queue!(out, Clear(ClearType::UntilNewLine))?;
+
+        let visible_lines = self.lines.iter().enumerate().skip(self.scrolled_to);
+        for (row, (i, line)) in (1..=height).zip(visible_lines) {
+            queue!(out, MoveTo(0, row as u16))?;
+            if i == self.selected {
+                write!(out, "{}", style(&line.text).reverse())?;
+            } else {
+                write!(out, "{}", line.text)?;
+            }
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+        queue!(out, Clear(ClearType::FromCursorDown))?;
+        out.flush()
+    }
+}
+
+impl RecentChange {
+    fn new(change: Change) -> Self {
+        RecentChange {
+            change,
+            updates_left: UPDATES_TO_SHOW_CHANGES,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: usize, command: &str) -> Process {
+        Process {
+            pid,
+            pgid: pid,
+            user: "root".to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    /// A tree of (PID, parent PID) pairs, each process named after its PID.
+    fn tree(pids_and_parent_pids: &[(usize, usize)]) -> AllProcessesTree {
+        AllProcessesTree::from_processes(
+            pids_and_parent_pids
+                .iter()
+                .map(|&(pid, parent_pid)| (process(pid, &format!("p{pid}")), parent_pid)),
+        )
+    }
+
+    fn options() -> Options {
+        Options {
+            color: false,
+            ..Options::default()
+        }
+    }
+
+    fn shown_pids(watch: &Watch) -> Vec<usize> {
+        watch.lines.iter().map(|line| line.pid).collect()
+    }
+
+    fn change(watch: &Watch, pid: usize) -> Option<&Change> {
+        watch.recent_changes.get(&pid).map(|recent| &recent.change)
+    }
+
+    fn press(watch: &mut Watch, code: KeyCode) -> KeyAction {
+        watch.handle_key(KeyEvent::from(code), 10)
+    }
+
+    #[test]
+    fn nothing_is_new_at_first() {
+        let mut watch = Watch::default();
+        watch.update(tree(&[(1, 0), (2, 1)]));
+        assert!(watch.recent_changes.is_empty());
+    }
+
+    #[test]
+    fn spawned_and_exited_processes() {
+        let mut watch = Watch::default();
+        watch.update(tree(&[(1, 0), (2, 1), (3, 2)]));
+
+        // 3 has exited, and 4 has been spawned
+        let updated = watch.update(tree(&[(1, 0), (2, 1), (4, 1)]));
+        assert!(matches!(change(&watch, 4), Some(Change::Spawned)));
+        assert!(matches!(change(&watch, 3), Some(Change::Exited { .. })));
+        assert!(change(&watch, 2).is_none());
+        // 3 is still in the tree, under its old parent
+        assert_eq!(updated.all_parent_pids_to_child_processes[&2][0].pid, 3);
+
+        // Both are shown for a few updates, and then 3 is gone for good.
+        for _ in 1..UPDATES_TO_SHOW_CHANGES {
+            watch.update(tree(&[(1, 0), (2, 1), (4, 1)]));
+            assert!(matches!(change(&watch, 4), Some(Change::Spawned)));
+        }
+        let updated = watch.update(tree(&[(1, 0), (2, 1), (4, 1)]));
+        assert!(watch.recent_changes.is_empty());
+        assert!(updated.find(3).is_none());
+    }
+
+    #[test]
+    fn changes_are_colored() {
+        let mut watch = Watch::default();
+        watch.update(tree(&[(1, 0), (2, 1)]));
+        let updated = watch.update(tree(&[(1, 0), (3, 1)]));
+        // Colors are what we're checking, so don't let NO_COLOR turn them off
+        crossterm::style::force_color_output(true);
+        watch.render(&updated, &Options::default());
+
+        assert_eq!(shown_pids(&watch), [1, 2, 3]);
+        let background = |color| style("").on(color).to_string().replace("\x1b[49m", "");
+        assert!(!watch.lines[0].text.contains("\x1b[48"));
+        assert!(watch.lines[1].text.starts_with(&background(Color::DarkRed)));
+        assert!(watch.lines[2]
+            .text
+            .starts_with(&background(Color::DarkGreen)));
+    }
+
+    #[test]
+    fn collapsing_subtrees() {
+        let mut watch = Watch::default();
+        let all = watch.update(tree(&[(1, 0), (2, 1), (3, 2), (4, 3), (5, 1)]));
+        watch.render(&all, &options());
+        assert_eq!(shown_pids(&watch), [1, 2, 3, 4, 5]);
+
+        press(&mut watch, KeyCode::Down);
+        press(&mut watch, KeyCode::Char(' '));
+        watch.render(&all, &options());
+        assert_eq!(shown_pids(&watch), [1, 2, 5]);
+        assert!(watch.lines[1].text.ends_with("p2 [+2]"));
+
+        // Collapsing 3 as well, while it's hidden under 2, shouldn't change what 2 hides.
+        watch.collapsed.insert(3);
+        watch.render(&all, &options());
+        assert!(watch.lines[1].text.ends_with("p2 [+2]"));
+
+        press(&mut watch, KeyCode::Right);
+        watch.render(&all, &options());
+        assert_eq!(shown_pids(&watch), [1, 2, 3, 5]);
+        assert!(watch.lines[2].text.ends_with("p3 [+1]"));
+    }
+
+    #[test]
+    fn selection_follows_its_process() {
+        let mut watch = Watch::default();
+        let all = watch.update(tree(&[(1, 0), (5, 1), (9, 1)]));
+        watch.render(&all, &options());
+        press(&mut watch, KeyCode::End);
+        assert_eq!(watch.selected_pid, Some(9));
+
+        // A new process comes before it, and it moves down a line.
+        let all = watch.update(tree(&[(1, 0), (3, 1), (5, 1), (9, 1)]));
+        watch.render(&all, &options());
+        assert_eq!((watch.selected, watch.selected_pid), (3, Some(9)));
+
+        press(&mut watch, KeyCode::PageUp);
+        assert_eq!(watch.selected_pid, Some(1));
+        press(&mut watch, KeyCode::Up);
+        assert_eq!(watch.selected_pid, Some(1));
+        assert_eq!(press(&mut watch, KeyCode::Char('q')), Quit);
+    }
+
+    #[test]
+    fn scrolls_to_the_selection() {
+        let pids: Vec<(usize, usize)> = (1..=20).map(|pid| (pid, pid - 1)).collect();
+        let mut watch = Watch::default();
+        let all = watch.update(tree(&pids));
+        watch.render(&all, &options());
+        let mut screen = Vec::new();
+
+        // Room for the status line and five lines of the tree
+        for _ in 0..7 {
+            press(&mut watch, KeyCode::Down);
+        }
+        watch
+            .draw(&mut screen, 6, Duration::from_secs(2), false)
+            .unwrap();
+        assert_eq!(watch.scrolled_to, 3);
+
+        press(&mut watch, KeyCode::Home);
+        watch
+            .draw(&mut screen, 6, Duration::from_secs(2), false)
+            .unwrap();
+        assert_eq!(watch.scrolled_to, 0);
+    }
+
+    #[test]
+    fn interval_is_part_of_the_watch_argument() {
+        let parse = |args: &[&str]| crate::parse_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            parse(&["--watch=0.5"]).watch_interval,
+            Some(Duration::from_millis(500))
+        );
+        // A number after --watch is a filter, not an interval
+        let options = parse(&["--watch", "8080"]);
+        assert_eq!(options.watch_interval, Some(Duration::from_secs(2)));
+        assert_eq!(options.filters.filters.len(), 1);
+    }
+}
@@ -1,40 +1,430 @@
-use crossterm::terminal::enable_raw_mode;
-use std::io::{self, Read, Write};
-use termios::{tcsetattr, Termios, TCSADRAIN};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Read, Stdout, Write};
+use std::thread;
+use std::time::Duration;
+
+mod morse;
+mod script;
+mod terminal;
+mod tone;
+mod trace;
+mod wav;
+
+use morse::Signal;
+use script::Step;
+use terminal::RawMode;
+use tone::{Tone, Waveform};
+use trace::Trace;
+use wav::WavWriter;
 
 const BELL_CHAR: u8 = 0x7;
 
-fn main() {
-    let terminal_attrs = Termios::from_fd(0).expect("Failed to initialize terminal attrs");
+const USAGE: &str = "Usage: beep-beep [--tones bell|beep|dtmf|notes] [--wave sine|square] \
+                     [--frequency HZ] [--duration MS] [--morse [--wpm N]] \
+                     [--delay MS] [--output FILE.wav|-] [--script FILE|- [--trace FILE]]";
 
-    enable_raw_mode().expect("Failed to enable raw mode");
+/// What each keypress turns into.
+#[derive(Clone, Copy, PartialEq)]
+enum Tones {
+    // A digit N writes N BEL characters to the terminal
+    Bell,
+    // A digit N plays N beeps at the configured frequency
+    Beep,
+    // Each key on a telephone keypad (digits, * and #) plays its touch tone
+    Dtmf,
+    // Each digit plays a note of the C major scale
+    Notes,
+}
 
-    let mut buffer: [u8; 1] = [0; 1];
+struct Args {
+    tones: Tones,
+    waveform: Waveform,
+    frequency: f32,
+    duration: Duration,
+    // Type letters instead of digits, and hear them in Morse code
+    morse: bool,
+    words_per_minute: u32,
+    // Between one bell and the next, and between the keys of a script
+    delay: Duration,
+    // A WAV file path, or "-" for stdout. Only for synthesized tones.
+    output: Option<String>,
+    // Play keys from a file (or stdin, if "-") instead of reading them from the terminal
+    script: Option<String>,
+    // Where to log what's played, when playing a script
+    trace: Option<String>,
+}
 
-    loop {
-        if let Err(e) = io::stdin().read(&mut buffer) {
-            tcsetattr(0, TCSADRAIN, &terminal_attrs).expect("Failed to restore terminal attrs");
-            eprintln!("Failed to read from stdin: {e}");
-            std::process::exit(1);
+/// Where the sounds go: BEL characters to the terminal, or synthesized audio as WAV.
+enum Sound {
+    Bells,
+    // Streamed WAV can't have its length filled in at the end, but `aplay` doesn't mind
+    Stream(WavWriter<Stdout>),
+    File(WavWriter<File>),
+}
+
+struct Output {
+    sound: Sound,
+    trace: Option<Trace>,
+}
+
+impl Output {
+    fn play(&mut self, args: &Args, key: u8) -> io::Result<()> {
+        self.log(format_args!("key {}", key.escape_ascii()))?;
+        if args.morse {
+            return self.play_morse(args, key);
         }
 
-        if buffer[0] == 0x3 {
-            // handle ctrl-c (at least on macOS)
-            tcsetattr(0, TCSADRAIN, &terminal_attrs).expect("Failed to restore terminal attrs");
-            std::process::exit(0);
+        match self.sound {
+            Sound::Bells if key.is_ascii_digit() => self.ring_bells(key - 0x30, args.delay),
+            Sound::Bells => Ok(()),
+            _ => self.play_tones(&tones_for_key(args, key), args.waveform),
         }
+    }
 
-        if buffer[0].is_ascii_digit() {
-            let num_bells = buffer[0] - 0x30;
+    fn ring_bells(&mut self, num_bells: u8, delay: Duration) -> io::Result<()> {
+        if delay.is_zero() {
             let bells = vec![BELL_CHAR; num_bells as usize];
-            if let Err(e) = io::stdout()
+            io::stdout()
                 .write_all(&bells)
-                .and_then(|_| io::stdout().flush())
-            {
-                tcsetattr(0, TCSADRAIN, &terminal_attrs).expect("Failed to restore terminal attrs");
-                eprintln!("Failed to write or flush to stdout: {e}");
-                std::process::exit(1);
+                .and_then(|_| io::stdout().flush())?;
+            for _ in 0..num_bells {
+                self.log(format_args!("bell"))?;
             }
+            return Ok(());
         }
+
+        for i in 0..num_bells {
+            if i > 0 {
+                self.pause(delay)?;
+            }
+            self.ring_bells(1, Duration::ZERO)?;
+        }
+        Ok(())
+    }
+
+    fn play_tones(&mut self, tones: &[Tone], waveform: Waveform) -> io::Result<()> {
+        for tone in tones {
+            if tone.frequencies.is_empty() {
+                self.pause(tone.duration)?;
+                continue;
+            }
+
+            self.write_samples(&tone::synthesize(tone, waveform))?;
+            let frequencies: Vec<String> = tone.frequencies.iter().map(f32::to_string).collect();
+            self.log(format_args!(
+                "tone {}Hz {}ms",
+                frequencies.join("+"),
+                tone.duration.as_millis()
+            ))?;
+            self.advance(tone.duration);
+        }
+        Ok(())
+    }
+
+    /// Nothing for a while: we sleep through it when ringing bells, but write it out as
+    /// silence in audio, since that's played back later at its own pace.
+    fn pause(&mut self, duration: Duration) -> io::Result<()> {
+        match self.sound {
+            Sound::Bells => thread::sleep(duration),
+            _ => self.write_samples(&tone::synthesize(&Tone::silence(duration), Waveform::Sine))?,
+        }
+        self.log(format_args!("pause {}ms", duration.as_millis()))?;
+        self.advance(duration);
+        Ok(())
     }
+
+    fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        match &mut self.sound {
+            Sound::Bells => Ok(()),
+            Sound::Stream(writer) => writer.write_samples(samples),
+            Sound::File(writer) => writer.write_samples(samples),
+        }
+    }
+
+    /// Sends the key as Morse code, echoing its dots and dashes as they go. The echo goes to
+    /// stderr, which is still the terminal when stdout is carrying audio.
+    fn play_morse(&mut self, args: &Args, key: u8) -> io::Result<()> {
+        let mut echo = io::stderr();
+        if key == b'\r' {
+            return echo.write_all(b"\r\n");
+        }
+        let Some(signals) = morse::signals(key) else {
+            return Ok(());
+        };
+
+        let unit = morse::unit(args.words_per_minute);
+        for signal in signals {
+            echo.write_all(signal.symbol().as_bytes())?;
+            let duration = unit * signal.units();
+            match (&self.sound, signal) {
+                (_, Signal::Gap(_)) => self.pause(duration)?,
+                // A bell can't be held like a tone, so all that tells a dash from a dot is
+                // the longer wait after it
+                (Sound::Bells, _) => {
+                    self.ring_bells(1, Duration::ZERO)?;
+                    self.pause(duration)?;
+                }
+                _ => {
+                    let tone = Tone {
+                        frequencies: vec![args.frequency],
+                        duration,
+                    };
+                    self.play_tones(&[tone], args.waveform)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn log(&mut self, event: fmt::Arguments) -> io::Result<()> {
+        match &mut self.trace {
+            Some(trace) => trace.event(event),
+            None => Ok(()),
+        }
+    }
+
+    fn advance(&mut self, duration: Duration) {
+        if let Some(trace) = &mut self.trace {
+            trace.advance(duration);
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        if let Some(trace) = self.trace {
+            trace.finish()?;
+        }
+        match self.sound {
+            Sound::File(writer) => writer.finish(),
+            Sound::Bells | Sound::Stream(_) => Ok(()),
+        }
+    }
+}
+
+fn tones_for_key(args: &Args, key: u8) -> Vec<Tone> {
+    let tone = |frequencies: Vec<f32>| Tone {
+        frequencies,
+        duration: args.duration,
+    };
+    match args.tones {
+        Tones::Bell => Vec::new(),
+        Tones::Beep if key.is_ascii_digit() => {
+            // Each beep is followed by an equally long gap, so they don't run together
+            let beep = [tone(vec![args.frequency]), Tone::silence(args.duration)];
+            beep.iter()
+                .cycle()
+                .take(2 * (key - b'0') as usize)
+                .cloned()
+                .collect()
+        }
+        Tones::Beep => Vec::new(),
+        Tones::Dtmf => tone::dtmf(key).map_or(Vec::new(), |pair| vec![tone(pair.to_vec())]),
+        Tones::Notes => tone::note(key).map_or(Vec::new(), |note| vec![tone(vec![note])]),
+    }
+}
+
+fn main() {
+    let args = parse_args();
+
+    let sound = match args.output.as_deref() {
+        None => Sound::Bells,
+        Some("-") => {
+            Sound::Stream(WavWriter::new(io::stdout()).expect("Failed to write to stdout"))
+        }
+        Some(path) => {
+            Sound::File(WavWriter::new(create_file(path)).expect("Failed to write WAV header"))
+        }
+    };
+    let trace = args
+        .trace
+        .as_deref()
+        .map(|path| Trace::new(BufWriter::new(create_file(path))));
+    let mut output = Output { sound, trace };
+
+    let result = match args.script.as_deref() {
+        Some(path) => run_script(&args, &mut output, path),
+        None => {
+            let _raw_mode = RawMode::enable(0).expect("Failed to enable raw mode");
+            beep_until_ctrl_c(&args, &mut output)
+        }
+    };
+    // The terminal is back to normal by here, so error messages come out properly
+
+    if let Err(msg) = result {
+        eprintln!("{msg}");
+        std::process::exit(1);
+    }
+    output.finish().expect("Failed to finish writing output");
+}
+
+fn create_file(path: &str) -> File {
+    File::create(path).unwrap_or_else(|e| {
+        eprintln!("Failed to create {path}: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn beep_until_ctrl_c(args: &Args, output: &mut Output) -> Result<(), String> {
+    let mut buffer: [u8; 1] = [0; 1];
+
+    loop {
+        let num_read = io::stdin()
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read from stdin: {e}"))?;
+
+        // handle ctrl-c (at least on macOS), or the end of input if the terminal goes away
+        if num_read == 0 || buffer[0] == 0x3 {
+            return Ok(());
+        }
+
+        output
+            .play(args, buffer[0])
+            .map_err(|e| format!("Failed to write or flush output: {e}"))?;
+    }
+}
+
+/// Plays a script from a file, or from stdin if the path is "-", without needing a terminal.
+fn run_script(args: &Args, output: &mut Output, path: &str) -> Result<(), String> {
+    let script = if path == "-" {
+        let mut script = Vec::new();
+        io::stdin()
+            .read_to_end(&mut script)
+            .map(|_| script)
+            .map_err(|e| format!("Failed to read script from stdin: {e}"))?
+    } else {
+        fs::read(path).map_err(|e| format!("Failed to read script {path}: {e}"))?
+    };
+    let name = if path == "-" { "stdin" } else { path };
+    let steps = script::parse(&script).map_err(|e| format!("Invalid script from {name}: {e}"))?;
+
+    let mut previous_step = None;
+    for step in steps {
+        let result = match (previous_step, step) {
+            // Ctrl-C stops a script early, just like it does when typing
+            (_, Step::Key(0x3)) => break,
+            (Some(Step::Key(_)), Step::Key(key)) if !args.delay.is_zero() => output
+                .pause(args.delay)
+                .and_then(|_| output.play(args, key)),
+            (_, Step::Key(key)) => output.play(args, key),
+            (_, Step::Pause(duration)) => output.pause(duration),
+        };
+        result.map_err(|e| format!("Failed to write or flush output: {e}"))?;
+        previous_step = Some(step);
+    }
+    Ok(())
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        tones: Tones::Bell,
+        waveform: Waveform::Sine,
+        frequency: 440.0,
+        duration: Duration::from_millis(200),
+        morse: false,
+        words_per_minute: 20,
+        delay: Duration::ZERO,
+        output: None,
+        script: None,
+        trace: None,
+    };
+    let mut raw_args = env::args().skip(1);
+
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--tones" => {
+                args.tones = match raw_args.next().as_deref() {
+                    Some("bell") => Tones::Bell,
+                    Some("beep") => Tones::Beep,
+                    Some("dtmf") => Tones::Dtmf,
+                    Some("notes") => Tones::Notes,
+                    _ => usage_error("--tones must be one of bell, beep, dtmf or notes"),
+                }
+            }
+            "--wave" => {
+                args.waveform = match raw_args.next().as_deref() {
+                    Some("sine") => Waveform::Sine,
+                    Some("square") => Waveform::Square,
+                    _ => usage_error("--wave must be one of sine or square"),
+                }
+            }
+            "--frequency" => {
+                args.frequency = raw_args
+                    .next()
+                    .and_then(|hz| hz.parse().ok())
+                    .filter(|hz: &f32| *hz > 0.0 && *hz < tone::SAMPLE_RATE as f32 / 2.0)
+                    .unwrap_or_else(|| usage_error("--frequency must be between 0 and 22050 Hz"))
+            }
+            "--duration" => {
+                args.duration = raw_args
+                    .next()
+                    .and_then(|ms| ms.parse().ok())
+                    .map(Duration::from_millis)
+                    .unwrap_or_else(|| usage_error("--duration must be a number of milliseconds"))
+            }
+            "--morse" => args.morse = true,
+            "--wpm" => {
+                args.words_per_minute = raw_args
+                    .next()
+                    .and_then(|wpm| wpm.parse().ok())
+                    .filter(|wpm| (1..=100).contains(wpm))
+                    .unwrap_or_else(|| usage_error("--wpm must be between 1 and 100"))
+            }
+            "--delay" => {
+                args.delay = raw_args
+                    .next()
+                    .and_then(|ms| ms.parse().ok())
+                    .map(Duration::from_millis)
+                    .unwrap_or_else(|| usage_error("--delay must be a number of milliseconds"))
+            }
+            "--script" => {
+                args.script = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--script must be given a file or -")),
+                )
+            }
+            "--trace" => {
+                args.trace = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--trace must be given a file")),
+                )
+            }
+            "--output" => {
+                args.output = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--output must be given a file or -")),
+                )
+            }
+            _ => usage_error(&format!("Unrecognized argument '{arg}'")),
+        }
+    }
+
+    // Without a terminal to type into, all we can do is read a script from stdin
+    if args.script.is_none() && !io::stdin().is_terminal() {
+        args.script = Some("-".to_string());
+    }
+    if args.trace.is_some() && args.script.is_none() {
+        usage_error("--trace only applies to scripts");
+    }
+    if args.morse && matches!(args.tones, Tones::Dtmf | Tones::Notes) {
+        usage_error("--morse only plays bells or beeps");
+    }
+    match (args.tones, &args.output) {
+        (Tones::Bell, Some(_)) => usage_error("--output only applies to synthesized tones"),
+        (Tones::Bell, None) => {}
+        (_, None) => usage_error("Synthesized tones need an --output"),
+        (_, Some(path)) if path == "-" && io::stdout().is_terminal() => {
+            usage_error("Refusing to write audio to a terminal; pipe it to aplay instead")
+        }
+        (_, Some(_)) => {}
+    }
+    args
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
 }
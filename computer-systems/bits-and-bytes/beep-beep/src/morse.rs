@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+// Every length in Morse code is a whole number of units, the length of a dot
+const DOT_UNITS: u32 = 1;
+const DASH_UNITS: u32 = 3;
+// Between the dots and dashes of one character
+const ELEMENT_GAP_UNITS: u32 = 1;
+// Between characters
+const LETTER_GAP_UNITS: u32 = 3;
+// Between words
+const WORD_GAP_UNITS: u32 = 7;
+
+/// One piece of a Morse transmission: a mark (the sound is on) or a gap (it's off).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+    Dot,
+    Dash,
+    Gap(u32),
+}
+
+impl Signal {
+    pub fn units(self) -> u32 {
+        match self {
+            Signal::Dot => DOT_UNITS,
+            Signal::Dash => DASH_UNITS,
+            Signal::Gap(units) => units,
+        }
+    }
+
+    /// How this looks written down: dots and dashes, with a space between letters and a
+    /// slash between words.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Signal::Dot => ".",
+            Signal::Dash => "-",
+            Signal::Gap(ELEMENT_GAP_UNITS) => "",
+            Signal::Gap(LETTER_GAP_UNITS) => " ",
+            Signal::Gap(_) => "/ ",
+        }
+    }
+}
+
+/// How long a unit lasts at the given speed. Speeds are measured by how many times a minute
+/// you could send "PARIS ", which is 50 units long with the gap after it.
+pub fn unit(words_per_minute: u32) -> Duration {
+    Duration::from_millis(60_000 / 50) / words_per_minute
+}
+
+/// International Morse code for a letter or digit, in either case.
+pub fn code(c: u8) -> Option<&'static str> {
+    const LETTERS: [&str; 26] = [
+        ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+        "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--",
+        "--..",
+    ];
+    const DIGITS: [&str; 10] = [
+        "-----", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
+    ];
+    match c.to_ascii_uppercase() {
+        letter @ b'A'..=b'Z' => Some(LETTERS[(letter - b'A') as usize]),
+        digit @ b'0'..=b'9' => Some(DIGITS[(digit - b'0') as usize]),
+        _ => None,
+    }
+}
+
+/// The signals to send for a typed key, including the gap after it. A space is a word gap,
+/// which is only the extra time on top of the letter gap already sent after the last letter.
+/// Returns `None` for keys with no Morse code.
+pub fn signals(key: u8) -> Option<Vec<Signal>> {
+    if key == b' ' {
+        return Some(vec![Signal::Gap(WORD_GAP_UNITS - LETTER_GAP_UNITS)]);
+    }
+
+    let mut signals = Vec::new();
+    for (i, element) in code(key)?.bytes().enumerate() {
+        if i > 0 {
+            signals.push(Signal::Gap(ELEMENT_GAP_UNITS));
+        }
+        signals.push(if element == b'.' {
+            Signal::Dot
+        } else {
+            Signal::Dash
+        });
+    }
+    signals.push(Signal::Gap(LETTER_GAP_UNITS));
+    Some(signals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Signal::*;
+
+    #[test]
+    fn codes() {
+        assert_eq!(code(b's'), Some("..."));
+        assert_eq!(code(b'O'), Some("---"));
+        assert_eq!(code(b'0'), Some("-----"));
+        assert_eq!(code(b'!'), None);
+    }
+
+    #[test]
+    fn letter_signals() {
+        assert_eq!(signals(b'a'), Some(vec![Dot, Gap(1), Dash, Gap(3)]));
+        assert_eq!(signals(b' '), Some(vec![Gap(4)]));
+        assert_eq!(signals(b'\r'), None);
+    }
+
+    #[test]
+    fn paris_is_fifty_units() {
+        let units: u32 = "PARIS "
+            .bytes()
+            .flat_map(|key| signals(key).unwrap())
+            .map(Signal::units)
+            .sum();
+        assert_eq!(units, 50);
+        assert_eq!(unit(20), Duration::from_millis(60));
+    }
+
+    #[test]
+    fn written_form() {
+        let written: String = "SOS OK"
+            .bytes()
+            .flat_map(|key| signals(key).unwrap())
+            .map(Signal::symbol)
+            .collect();
+        assert_eq!(written, "... --- ... / --- -.- ");
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::time::Duration;
+
+/// How long a `,` in a script pauses for, like the pause key on a phone dialer.
+pub const COMMA_PAUSE: Duration = Duration::from_millis(500);
+
+/// One step of a script: a key pressed just as if it had been typed, or a pause.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Key(u8),
+    Pause(Duration),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parses a script. Every byte is a key, except for:
+///
+/// - `,`, which pauses for `COMMA_PAUSE`
+/// - `[250ms]` or `[2s]`, which pause for that long
+/// - `\`, which starts an escape: `\,`, `\[` and `\\` for those keys themselves, `\n`, `\r` and
+///   `\t`, or `\xHH` for any byte, like `\x03` for Ctrl-C to stop early
+pub fn parse(script: &[u8]) -> Result<Vec<Step>, ScriptError> {
+    let mut steps = Vec::new();
+    let mut line = 1;
+    let mut bytes = script.iter().copied();
+    let error = |line, message: &str| ScriptError {
+        line,
+        message: message.to_string(),
+    };
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b',' => steps.push(Step::Pause(COMMA_PAUSE)),
+            b'[' => {
+                // Errors point at the line the pause starts on, but the lines after it still
+                // have to be counted from wherever it ends.
+                let start_line = line;
+                let mut length = Vec::new();
+                loop {
+                    match bytes.next() {
+                        Some(b']') => break,
+                        Some(byte) => {
+                            if byte == b'\n' {
+                                line += 1;
+                            }
+                            length.push(byte);
+                        }
+                        None => return Err(error(start_line, "Unterminated '['")),
+                    }
+                }
+                let length = String::from_utf8_lossy(&length);
+                let pause = parse_duration(&length)
+                    .ok_or_else(|| error(start_line, &format!("Invalid pause '[{length}]'")))?;
+                steps.push(Step::Pause(pause));
+            }
+            b'\\' => {
+                let key = match bytes.next() {
+                    Some(b'n') => b'\n',
+                    Some(b'r') => b'\r',
+                    Some(b't') => b'\t',
+                    Some(b'x') => {
+                        let hex = [bytes.next(), bytes.next()];
+                        let hex: Option<Vec<u8>> = hex.into_iter().collect();
+                        hex.and_then(|hex| String::from_utf8(hex).ok())
+                            .and_then(|hex| u8::from_str_radix(&hex, 16).ok())
+                            .ok_or_else(|| error(line, "\\x must be followed by two hex digits"))?
+                    }
+                    Some(key @ (b',' | b'[' | b'\\')) => key,
+                    Some(other) => {
+                        return Err(error(
+                            line,
+                            &format!("Unknown escape '\\{}'", other.escape_ascii()),
+                        ))
+                    }
+                    None => return Err(error(line, "Script ends in the middle of an escape")),
+                };
+                steps.push(Step::Key(key));
+            }
+            _ => {
+                if byte == b'\n' {
+                    line += 1;
+                }
+                steps.push(Step::Key(byte));
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+fn parse_duration(length: &str) -> Option<Duration> {
+    if let Some(ms) = length.strip_suffix("ms") {
+        ms.parse().ok().map(Duration::from_millis)
+    } else if let Some(secs) = length.strip_suffix('s') {
+        secs.parse().ok().map(Duration::from_secs)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Step::*;
+
+    #[test]
+    fn keys_and_pauses() {
+        assert_eq!(
+            parse(b"12,3[250ms]#[2s]").unwrap(),
+            vec![
+                Key(b'1'),
+                Key(b'2'),
+                Pause(COMMA_PAUSE),
+                Key(b'3'),
+                Pause(Duration::from_millis(250)),
+                Key(b'#'),
+                Pause(Duration::from_secs(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse(br"\,\[\\\n\x03").unwrap(),
+            vec![Key(b','), Key(b'['), Key(b'\\'), Key(b'\n'), Key(0x03)]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse(b"1\n2[soon]"),
+            Err(ScriptError {
+                line: 2,
+                message: "Invalid pause '[soon]'".to_string()
+            })
+        );
+        assert_eq!(parse(b"1[250ms").unwrap_err().message, "Unterminated '['");
+        assert_eq!(parse(b"1[").unwrap_err().message, "Unterminated '['");
+        assert_eq!(parse(b"1\n[\n2\n").unwrap_err().line, 2);
+        assert_eq!(parse(br"\q").unwrap_err().message, "Unknown escape '\\q'");
+        assert_eq!(
+            parse(br"\x3").unwrap_err().message,
+            "\\x must be followed by two hex digits"
+        );
+        assert_eq!(
+            parse(b"\\").unwrap_err().message,
+            "Script ends in the middle of an escape"
+        );
+    }
+}
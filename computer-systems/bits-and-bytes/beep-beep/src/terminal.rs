@@ -0,0 +1,190 @@
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::{Handle, Signals};
+use signal_hook::{flag, low_level};
+use std::io;
+use std::os::fd::RawFd;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::thread::{self, JoinHandle};
+use termios::{cfmakeraw, tcsetattr, Termios, TCSADRAIN};
+
+const SIGNALS: [i32; 3] = [SIGHUP, SIGINT, SIGTERM];
+
+// The terminal the panic hook should restore, and what to, while a `RawMode` is alive. The
+// hook itself is installed once and stays installed, so it has to be told when there's
+// nothing to do.
+static RESTORE_ON_PANIC: Mutex<Option<(RawFd, Termios)>> = Mutex::new(None);
+
+// Set whenever there's no `RawMode` alive, so that the signals above take their default
+// action, just as if we'd never handled them.
+static SIGNALS_TAKE_DEFAULT_ACTION: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Keeps a terminal in raw mode for as long as it's alive, and puts back the attributes it
+/// had before when dropped. Raw mode outlives the process otherwise, leaving the shell we
+/// return to with no echo and no line editing, so we also restore them when we panic or are
+/// killed by a signal.
+pub struct RawMode {
+    fd: RawFd,
+    saved_attrs: Termios,
+    signals: Handle,
+    signal_thread: Option<JoinHandle<()>>,
+}
+
+impl RawMode {
+    pub fn enable(fd: RawFd) -> io::Result<Self> {
+        let saved_attrs = Termios::from_fd(fd)?;
+        let mut raw_attrs = saved_attrs;
+        cfmakeraw(&mut raw_attrs);
+
+        let signals_take_default_action = install_once()?;
+        let (signals, signal_thread) = restore_on_signals(fd, saved_attrs)?;
+        signals_take_default_action.store(false, Ordering::SeqCst);
+        *RESTORE_ON_PANIC.lock().unwrap() = Some((fd, saved_attrs));
+        // If this fails, dropping the guard undoes all of the above.
+        let raw_mode = RawMode {
+            fd,
+            saved_attrs,
+            signals,
+            signal_thread: Some(signal_thread),
+        };
+        tcsetattr(fd, TCSADRAIN, &raw_attrs)?;
+
+        Ok(raw_mode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Ok(mut restore_on_panic) = RESTORE_ON_PANIC.lock() {
+            *restore_on_panic = None;
+        }
+        // Nothing useful to do if this fails; the terminal has probably gone away
+        let _ = tcsetattr(self.fd, TCSADRAIN, &self.saved_attrs);
+
+        // Only now can a signal take its default action. One that arrives before this still
+        // reaches the thread, which kills us after all, so none of them go missing.
+        if let Some(default_action) = SIGNALS_TAKE_DEFAULT_ACTION.get() {
+            default_action.store(true, Ordering::SeqCst);
+        }
+        // Stop the thread, and with it our handling of the signals, so it can't restore these
+        // attributes later on, after someone else has changed them.
+        self.signals.close();
+        if let Some(signal_thread) = self.signal_thread.take() {
+            let _ = signal_thread.join();
+        }
+    }
+}
+
+/// Raw mode turns off ISIG, so Ctrl-C arrives as a byte instead of SIGINT. Signals can still
+/// come from elsewhere though: `kill`, or SIGHUP when the terminal is closed. Their default
+/// action ends the process without unwinding, so `RawMode` would never be dropped. Instead
+/// we catch them on a thread, restore the terminal, and then take the default action after
+/// all, so that whoever started us sees that we were killed by the signal.
+fn restore_on_signals(fd: RawFd, saved_attrs: Termios) -> io::Result<(Handle, JoinHandle<()>)> {
+    let mut signals = Signals::new(SIGNALS)?;
+    let handle = signals.handle();
+    let signal_thread = thread::spawn(move || {
+        // Once the handle is closed, this returns None instead
+        if let Some(signal) = signals.forever().next() {
+            let _ = tcsetattr(fd, TCSADRAIN, &saved_attrs);
+            let _ = low_level::emulate_default_handler(signal);
+        }
+    });
+    Ok((handle, signal_thread))
+}
+
+/// Install the panic hook, and the signal handlers that take the default action when we're
+/// not in raw mode, the first time we're called. Every `RawMode` shares them, rather than
+/// adding another each time.
+fn install_once() -> io::Result<&'static AtomicBool> {
+    static INSTALL: Once = Once::new();
+    let mut result = Ok(());
+    INSTALL.call_once(|| result = install());
+    result?;
+    SIGNALS_TAKE_DEFAULT_ACTION
+        .get()
+        .map(|default_action| default_action.as_ref())
+        .ok_or_else(|| io::Error::other("Signal handlers failed to install earlier"))
+}
+
+fn install() -> io::Result<()> {
+    let default_action = Arc::new(AtomicBool::new(true));
+    for signal in SIGNALS {
+        flag::register_conditional_default(signal, Arc::clone(&default_action))?;
+    }
+    let _ = SIGNALS_TAKE_DEFAULT_ACTION.set(default_action);
+
+    // Dropping `RawMode` while unwinding would restore the terminal too, but only after the
+    // default hook has printed the panic message. In raw mode that message comes out without
+    // carriage returns, so we restore first.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // try_lock, since we might be panicking with the lock held
+        if let Ok(restore_on_panic) = RESTORE_ON_PANIC.try_lock() {
+            if let Some((fd, saved_attrs)) = *restore_on_panic {
+                let _ = tcsetattr(fd, TCSADRAIN, &saved_attrs);
+            }
+        }
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use termios::{ECHO, ICANON};
+
+    fn open_pty() -> (RawFd, RawFd) {
+        let (mut master, mut slave) = (0, 0);
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+            )
+        };
+        assert_eq!(result, 0, "openpty failed: {}", io::Error::last_os_error());
+        (master, slave)
+    }
+
+    #[test]
+    fn restores_on_drop_and_panic() {
+        let (master, slave) = open_pty();
+        let original = Termios::from_fd(slave).unwrap();
+        assert_ne!(original.c_lflag & (ECHO | ICANON), 0);
+
+        let raw_mode = RawMode::enable(slave).unwrap();
+        assert_eq!(
+            Termios::from_fd(slave).unwrap().c_lflag & (ECHO | ICANON),
+            0
+        );
+        drop(raw_mode);
+        assert_eq!(Termios::from_fd(slave).unwrap(), original);
+
+        let result = panic::catch_unwind(|| {
+            let _raw_mode = RawMode::enable(slave).unwrap();
+            panic!("on purpose");
+        });
+        assert!(result.is_err());
+        assert_eq!(Termios::from_fd(slave).unwrap(), original);
+
+        // Once the guard's gone, a later panic mustn't put back attributes that someone else
+        // has changed since.
+        let mut changed = original;
+        changed.c_lflag &= !ECHO;
+        tcsetattr(slave, TCSADRAIN, &changed).unwrap();
+        let result = panic::catch_unwind(|| panic!("on purpose, again"));
+        assert!(result.is_err());
+        assert_eq!(Termios::from_fd(slave).unwrap(), changed);
+
+        unsafe {
+            libc::close(slave);
+            libc::close(master);
+        }
+    }
+}
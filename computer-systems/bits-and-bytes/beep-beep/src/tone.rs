@@ -0,0 +1,152 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+// Leaves some headroom, so that two tones summed together (as in DTMF) can't clip
+const AMPLITUDE: f32 = 0.8 * i16::MAX as f32;
+// Starting or stopping a wave mid-cycle makes an audible click, so we ramp the volume up and
+// down over this long at each end of a tone.
+const FADE: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Square,
+}
+
+/// Some number of frequencies played together for a while. A single frequency is a plain
+/// beep; DTMF tones are two at once. No frequencies at all is silence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tone {
+    pub frequencies: Vec<f32>,
+    pub duration: Duration,
+}
+
+impl Tone {
+    pub fn silence(duration: Duration) -> Self {
+        Tone {
+            frequencies: Vec::new(),
+            duration,
+        }
+    }
+}
+
+/// Signed 16-bit mono PCM samples for the given tone, at `SAMPLE_RATE`.
+pub fn synthesize(tone: &Tone, waveform: Waveform) -> Vec<i16> {
+    let num_samples = (tone.duration.as_secs_f32() * SAMPLE_RATE as f32).round() as usize;
+    let num_fade_samples =
+        ((FADE.as_secs_f32() * SAMPLE_RATE as f32) as usize).min(num_samples / 2);
+
+    (0..num_samples)
+        .map(|i| {
+            if tone.frequencies.is_empty() {
+                return 0;
+            }
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let sum: f32 = tone
+                .frequencies
+                .iter()
+                .map(|frequency| {
+                    let sine = (TAU * frequency * t).sin();
+                    match waveform {
+                        Waveform::Sine => sine,
+                        Waveform::Square => sine.signum(),
+                    }
+                })
+                .sum();
+
+            let samples_from_edge = i.min(num_samples - 1 - i);
+            let fade = if samples_from_edge < num_fade_samples {
+                samples_from_edge as f32 / num_fade_samples as f32
+            } else {
+                1.0
+            };
+            (sum / tone.frequencies.len() as f32 * fade * AMPLITUDE) as i16
+        })
+        .collect()
+}
+
+/// The pair of frequencies for a key on a telephone keypad: one for its row, one for its
+/// column. Returns `None` for keys that aren't on a keypad.
+pub fn dtmf(key: u8) -> Option<[f32; 2]> {
+    const ROWS: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+    const COLUMNS: [f32; 3] = [1209.0, 1336.0, 1477.0];
+    let (row, column) = match key {
+        b'1'..=b'9' => ((key - b'1') / 3, (key - b'1') % 3),
+        b'*' => (3, 0),
+        b'0' => (3, 1),
+        b'#' => (3, 2),
+        _ => return None,
+    };
+    Some([ROWS[row as usize], COLUMNS[column as usize]])
+}
+
+/// The C major scale, with 1 as middle C up through 8 as the C an octave above, then 9 as the
+/// D after that. 0 is the B just below middle C, so every digit has a note.
+pub fn note(digit: u8) -> Option<f32> {
+    // Semitones above middle C for each digit
+    const SEMITONES: [i32; 10] = [-1, 0, 2, 4, 5, 7, 9, 11, 12, 14];
+    const MIDDLE_C: f32 = 261.63;
+    let semitones = SEMITONES.get(digit.checked_sub(b'0')? as usize)?;
+    Some(MIDDLE_C * 2f32.powf(*semitones as f32 / 12.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dtmf_keypad() {
+        assert_eq!(dtmf(b'1'), Some([697.0, 1209.0]));
+        assert_eq!(dtmf(b'5'), Some([770.0, 1336.0]));
+        assert_eq!(dtmf(b'9'), Some([852.0, 1477.0]));
+        assert_eq!(dtmf(b'0'), Some([941.0, 1336.0]));
+        assert_eq!(dtmf(b'#'), Some([941.0, 1477.0]));
+        assert_eq!(dtmf(b'a'), None);
+    }
+
+    #[test]
+    fn notes() {
+        assert!((note(b'1').unwrap() - 261.63).abs() < 0.01);
+        // A above middle C
+        assert!((note(b'6').unwrap() - 440.0).abs() < 0.1);
+        // An octave up doubles the frequency
+        assert!((note(b'8').unwrap() - 2.0 * note(b'1').unwrap()).abs() < 0.01);
+        assert_eq!(note(b'x'), None);
+    }
+
+    #[test]
+    fn synthesize_length_and_fade() {
+        let tone = Tone {
+            frequencies: vec![440.0],
+            duration: Duration::from_millis(100),
+        };
+        let samples = synthesize(&tone, Waveform::Sine);
+        assert_eq!(samples.len(), SAMPLE_RATE as usize / 10);
+        // Faded in and out, so no click at either end
+        assert_eq!(samples[0], 0);
+        assert_eq!(samples[samples.len() - 1], 0);
+        assert!(samples.iter().any(|&sample| sample > i16::MAX / 2));
+    }
+
+    #[test]
+    fn square_wave_is_two_levels() {
+        let tone = Tone {
+            frequencies: vec![100.0],
+            duration: Duration::from_millis(100),
+        };
+        let samples = synthesize(&tone, Waveform::Square);
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        assert!(middle
+            .iter()
+            .all(|&sample| sample.unsigned_abs() as f32 >= AMPLITUDE - 1.0 || sample == 0));
+    }
+
+    #[test]
+    fn silence() {
+        let samples = synthesize(&Tone::silence(Duration::from_millis(10)), Waveform::Sine);
+        assert_eq!(samples.len(), 441);
+        assert!(samples.iter().all(|&sample| sample == 0));
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// A log of everything played, one event per line, each stamped with when it happens.
+///
+/// The time isn't read off a clock. Instead it's how much has been played so far: the length
+/// of every tone, pause and delay added up. That's the same thing for bells, which we sleep
+/// through, but for audio it's the position in the recording, however fast we wrote it. And
+/// either way, the same script gives the same trace every time.
+pub struct Trace {
+    writer: Box<dyn Write>,
+    elapsed: Duration,
+}
+
+impl Trace {
+    pub fn new(writer: impl Write + 'static) -> Self {
+        Trace {
+            writer: Box::new(writer),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn event(&mut self, event: fmt::Arguments) -> io::Result<()> {
+        writeln!(self.writer, "{:8.3} {event}", self.elapsed.as_secs_f64())
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.elapsed += duration;
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.event(format_args!("end"))?;
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::tone::SAMPLE_RATE;
+
+const NUM_CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+const HEADER_LEN: u32 = 44;
+// What the RIFF and data chunk sizes say while we don't know them yet. Players like `aplay`
+// treat this as "read until the input ends", which is what we want when streaming.
+const UNKNOWN_LEN: u32 = u32::MAX;
+
+/// Writes 16-bit mono PCM as a WAV file, one batch of samples at a time, so that it can be
+/// played as it's written (e.g. piped to `aplay`).
+pub struct WavWriter<W: Write> {
+    writer: W,
+    num_samples: u32,
+}
+
+impl<W: Write> WavWriter<W> {
+    /// Writes the WAV header, with the length left unknown.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&header(UNKNOWN_LEN))?;
+        writer.flush()?;
+        Ok(WavWriter {
+            writer,
+            num_samples: 0,
+        })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        self.writer.write_all(&bytes)?;
+        self.writer.flush()?;
+        self.num_samples = self.num_samples.saturating_add(samples.len() as u32);
+        Ok(())
+    }
+}
+
+impl WavWriter<File> {
+    /// Now that we know how many samples there are, go back and fill the real lengths in to
+    /// the header. Only possible for files; a stream just keeps the unknown length.
+    pub fn finish(mut self) -> io::Result<()> {
+        // Too long for a WAV file to say, once the count has saturated. Saturating here too
+        // leaves the unknown length, so players still read until the data ends.
+        let data_len = self
+            .num_samples
+            .saturating_mul((BITS_PER_SAMPLE / 8) as u32);
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header(data_len))?;
+        self.writer.flush()
+    }
+}
+
+/// The 44-byte header of a WAV file holding `data_len` bytes of PCM samples.
+fn header(data_len: u32) -> [u8; HEADER_LEN as usize] {
+    let block_align = NUM_CHANNELS * BITS_PER_SAMPLE / 8;
+    let byte_rate = SAMPLE_RATE * block_align as u32;
+    // Everything after the RIFF chunk's own 8-byte tag and size
+    let riff_len = data_len.saturating_add(HEADER_LEN - 8);
+
+    let mut header = [0; HEADER_LEN as usize];
+    let fields: [&[u8]; 13] = [
+        b"RIFF",
+        &riff_len.to_le_bytes(),
+        b"WAVE",
+        b"fmt ",
+        &16u32.to_le_bytes(), // length of the rest of the fmt chunk
+        &1u16.to_le_bytes(),  // uncompressed PCM
+        &NUM_CHANNELS.to_le_bytes(),
+        &SAMPLE_RATE.to_le_bytes(),
+        &byte_rate.to_le_bytes(),
+        &block_align.to_le_bytes(),
+        &BITS_PER_SAMPLE.to_le_bytes(),
+        b"data",
+        &data_len.to_le_bytes(),
+    ];
+    let mut offset = 0;
+    for field in fields {
+        header[offset..offset + field.len()].copy_from_slice(field);
+        offset += field.len();
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_layout() {
+        let header = header(8);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(&header[4..8], &44u32.to_le_bytes()[..]);
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        assert_eq!(&header[24..28], &SAMPLE_RATE.to_le_bytes()[..]);
+        assert_eq!(&header[28..32], &(SAMPLE_RATE * 2).to_le_bytes()[..]);
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(&header[40..44], &8u32.to_le_bytes()[..]);
+    }
+
+    #[test]
+    fn streaming_has_unknown_length() {
+        let mut writer = WavWriter::new(Vec::new()).unwrap();
+        writer.write_samples(&[1, -1]).unwrap();
+        let bytes = writer.writer;
+        assert_eq!(&bytes[40..44], &u32::MAX.to_le_bytes()[..]);
+        assert_eq!(&bytes[44..], &[0x01, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn finished_file_has_real_length() {
+        let path = std::env::temp_dir().join(format!("beep-beep-test-{}.wav", std::process::id()));
+        let mut writer = WavWriter::new(File::create(&path).unwrap()).unwrap();
+        writer.write_samples(&[0; 10]).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes.len(), 64);
+        assert_eq!(&bytes[4..8], &56u32.to_le_bytes()[..]);
+        assert_eq!(&bytes[40..44], &20u32.to_le_bytes()[..]);
+    }
+
+    #[test]
+    fn finishing_too_long_a_file_keeps_unknown_length() {
+        let path =
+            std::env::temp_dir().join(format!("beep-beep-test-long-{}.wav", std::process::id()));
+        let mut writer = WavWriter::new(File::create(&path).unwrap()).unwrap();
+        writer.num_samples = u32::MAX;
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[4..8], &u32::MAX.to_le_bytes()[..]);
+        assert_eq!(&bytes[40..44], &UNKNOWN_LEN.to_le_bytes()[..]);
+    }
+}
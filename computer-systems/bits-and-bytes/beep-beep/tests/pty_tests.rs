@@ -0,0 +1,200 @@
+// These run beep-beep the way a person would: attached to a terminal, albeit a pseudo-terminal
+// that we hold the other end of. That way we can check what state it leaves the terminal in,
+// however it exits.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+use termios::{Termios, ECHO, ICANON};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Pty {
+    // What we write here, beep-beep reads as typed input
+    master: File,
+    // The terminal end, which beep-beep reads from. We keep our own copy open so we can inspect
+    // its attributes after beep-beep exits.
+    slave: OwnedFd,
+}
+
+impl Pty {
+    fn attrs(&self) -> Termios {
+        Termios::from_fd(self.slave.as_raw_fd()).unwrap()
+    }
+
+    fn is_raw(&self) -> bool {
+        self.attrs().c_lflag & (ECHO | ICANON) == 0
+    }
+}
+
+fn open_pty() -> Pty {
+    let (mut master, mut slave) = (0, 0);
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null(),
+        )
+    };
+    assert_eq!(result, 0, "openpty failed");
+    let pty = unsafe {
+        Pty {
+            master: File::from_raw_fd(master),
+            slave: OwnedFd::from_raw_fd(slave),
+        }
+    };
+    assert!(!pty.is_raw());
+    pty
+}
+
+/// Starts beep-beep reading from the given pty, and waits until it has put it into raw mode.
+fn spawn_in_pty(pty: &Pty, args: &[&str], stdout: Stdio) -> Child {
+    let child = Command::new(env!("CARGO_BIN_EXE_beep-beep"))
+        .args(args)
+        .stdin(pty.slave.try_clone().unwrap())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    wait_until("beep-beep to enable raw mode", || pty.is_raw());
+    child
+}
+
+fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < TIMEOUT, "Timed out waiting for {what}");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn wait_for_exit(child: &mut Child) -> ExitStatus {
+    let mut status = None;
+    wait_until("beep-beep to exit", || {
+        status = child.try_wait().unwrap();
+        status.is_some()
+    });
+    status.unwrap()
+}
+
+fn kill(child: &Child, signal: i32) {
+    let result = unsafe { libc::kill(child.id() as i32, signal) };
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn restores_after_ctrl_c() {
+    let mut pty = open_pty();
+    let original_attrs = pty.attrs();
+    let mut child = spawn_in_pty(&pty, &[], pty.slave.try_clone().unwrap().into());
+
+    pty.master.write_all(b"12\x03").unwrap();
+    let status = wait_for_exit(&mut child);
+
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(pty.attrs(), original_attrs);
+    let mut output = [0; 3];
+    pty.master.read_exact(&mut output).unwrap();
+    assert_eq!(output, [0x7; 3]);
+}
+
+#[test]
+fn restores_after_signals() {
+    for signal in [libc::SIGTERM, libc::SIGHUP, libc::SIGINT] {
+        let pty = open_pty();
+        let original_attrs = pty.attrs();
+        let mut child = spawn_in_pty(&pty, &[], Stdio::null());
+
+        kill(&child, signal);
+        let status = wait_for_exit(&mut child);
+
+        // Killed by the signal, after restoring the terminal, rather than exiting
+        assert_eq!(status.signal(), Some(signal));
+        assert_eq!(pty.attrs(), original_attrs);
+    }
+}
+
+#[test]
+fn restores_after_panic() {
+    let mut pty = open_pty();
+    let original_attrs = pty.attrs();
+    // Far too many samples to allocate, which is the one way to make beep-beep panic
+    let args = [
+        "--tones",
+        "beep",
+        "--output",
+        "-",
+        "--duration",
+        "18446744073709551615",
+    ];
+    let mut child = spawn_in_pty(&pty, &args, Stdio::null());
+
+    pty.master.write_all(b"1").unwrap();
+    let status = wait_for_exit(&mut child);
+
+    assert_eq!(status.code(), Some(101));
+    assert_eq!(pty.attrs(), original_attrs);
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert!(stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn restores_after_write_error() {
+    let mut pty = open_pty();
+    let original_attrs = pty.attrs();
+    let mut child = spawn_in_pty(&pty, &["--tones", "dtmf", "--output", "-"], Stdio::piped());
+    // Nobody's listening to the audio any more, so playing anything fails
+    drop(child.stdout.take());
+
+    pty.master.write_all(b"5").unwrap();
+    let status = wait_for_exit(&mut child);
+
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(pty.attrs(), original_attrs);
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert!(
+        stderr.contains("Failed to write or flush output"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn morse_echoes_dots_and_dashes() {
+    let mut pty = open_pty();
+    let original_attrs = pty.attrs();
+    let mut child = spawn_in_pty(&pty, &["--morse", "--wpm", "100"], Stdio::null());
+
+    pty.master.write_all(b"sos ok\r\x03").unwrap();
+    let status = wait_for_exit(&mut child);
+
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(pty.attrs(), original_attrs);
+    let mut echo = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut echo)
+        .unwrap();
+    assert_eq!(echo, "... --- ... / --- -.- \r\n");
+}
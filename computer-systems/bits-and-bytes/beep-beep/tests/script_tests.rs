@@ -0,0 +1,132 @@
+// Scripts don't need a terminal, so these just pipe one in and check what comes out.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn run_script(script: &str, args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_beep-beep"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// The timestamps are right-aligned, which is easier to read in a file than in a test
+fn trace_lines(trace: &str) -> Vec<&str> {
+    trace.lines().map(str::trim_start).collect()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("beep-beep-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn bells_from_stdin() {
+    let output = run_script("1,2\n", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0x7; 3]);
+}
+
+#[test]
+fn ctrl_c_stops_a_script() {
+    let output = run_script(r"2\x039", &["--script", "-"]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0x7; 2]);
+}
+
+#[test]
+fn trace_with_delays() {
+    let trace_path = temp_path("trace.log");
+    let output = run_script(
+        "12[20ms]1",
+        &["--delay", "10", "--trace", trace_path.to_str().unwrap()],
+    );
+    let trace = fs::read_to_string(&trace_path).unwrap();
+    fs::remove_file(&trace_path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0x7; 4]);
+    assert_eq!(
+        trace_lines(&trace),
+        [
+            "0.000 key 1",
+            "0.000 bell",
+            "0.000 pause 10ms",
+            "0.010 key 2",
+            "0.010 bell",
+            "0.010 pause 10ms",
+            "0.020 bell",
+            "0.020 pause 20ms",
+            "0.040 key 1",
+            "0.040 bell",
+            "0.040 end",
+        ]
+    );
+}
+
+#[test]
+fn trace_of_tones_follows_the_audio() {
+    let script_path = temp_path("script.txt");
+    let wav_path = temp_path("tones.wav");
+    let trace_path = temp_path("tones.log");
+    fs::write(&script_path, "1,#").unwrap();
+    let output = run_script(
+        "",
+        &[
+            "--tones",
+            "dtmf",
+            "--duration",
+            "100",
+            "--output",
+            wav_path.to_str().unwrap(),
+            "--script",
+            script_path.to_str().unwrap(),
+            "--trace",
+            trace_path.to_str().unwrap(),
+        ],
+    );
+    let wav = fs::read(&wav_path).unwrap();
+    let trace = fs::read_to_string(&trace_path).unwrap();
+    for path in [script_path, wav_path, trace_path] {
+        fs::remove_file(path).unwrap();
+    }
+
+    assert!(output.status.success());
+    // 0.7 seconds of 16-bit samples at 44.1kHz, after the header
+    assert_eq!(wav.len(), 44 + 2 * 44_100 * 7 / 10);
+    assert_eq!(
+        trace_lines(&trace),
+        [
+            "0.000 key 1",
+            "0.000 tone 697+1209Hz 100ms",
+            "0.100 pause 500ms",
+            "0.600 key #",
+            "0.600 tone 941+1477Hz 100ms",
+            "0.700 end",
+        ]
+    );
+}
+
+#[test]
+fn invalid_script() {
+    let output = run_script("1[soon]", &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Invalid script from stdin: line 1: Invalid pause '[soon]'\n"
+    );
+}
@@ -0,0 +1,259 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use varint::framing::{FrameError, Frames, DEFAULT_MAX_MESSAGE_SIZE};
+
+mod render;
+
+const USAGE: &str = "Usage: decode_message [--input hex|base64|raw] \
+                     [--proto FILE.proto --type pkg.Msg] \
+                     [--delimited [--max-size BYTES] [--count | --record N [--extract]]] [FILE]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputFormat {
+    Hex,
+    Base64,
+    Raw,
+}
+
+struct Args {
+    input: InputFormat,
+    // We read from stdin unless given a file path.
+    path: Option<String>,
+    // With a schema, fields are shown by name and with their declared types. Without one,
+    // we can only guess at what each record is.
+    schema: Option<(String, String)>,
+    // The input is a stream of length-prefixed messages rather than a single message
+    delimited: Option<Delimited>,
+}
+
+struct Delimited {
+    max_message_size: usize,
+    action: DelimitedAction,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DelimitedAction {
+    // Render every record, one after another
+    RenderAll,
+    Count,
+    // Render only the record with this (zero-based) index
+    Render(usize),
+    // Write out the raw bytes of the record with this index, to pipe into other tools
+    Extract(usize),
+}
+
+fn main() {
+    let args = parse_args();
+
+    let mut input = Vec::new();
+    match &args.path {
+        Some(path) => {
+            input = fs::read(path).unwrap_or_else(|e| {
+                eprintln!("Failed to read {path}: {e}");
+                std::process::exit(1);
+            })
+        }
+        None => {
+            io::stdin()
+                .read_to_end(&mut input)
+                .expect("Failed to read from stdin");
+        }
+    }
+
+    // Hex and base64 inputs are allowed to be split across lines or otherwise padded with
+    // whitespace, since that's how they tend to show up when copied out of logs.
+    let bytes = match args.input {
+        InputFormat::Raw => input,
+        InputFormat::Hex => {
+            hex::decode(strip_whitespace(&input)).expect("please provide valid hexadecimal input")
+        }
+        InputFormat::Base64 => BASE64_STANDARD
+            .decode(strip_whitespace(&input))
+            .expect("please provide valid base64 input"),
+    };
+
+    let schema = args.schema.as_ref().map(|(proto_path, type_name)| {
+        let source = fs::read_to_string(proto_path).unwrap_or_else(|e| {
+            eprintln!("Failed to read {proto_path}: {e}");
+            std::process::exit(1);
+        });
+        let schema = proto_schema::parse(&source).unwrap_or_else(|e| {
+            eprintln!("Failed to parse {proto_path}: {e}");
+            std::process::exit(1);
+        });
+        (schema, type_name.as_str())
+    });
+    let render_message = |bytes: &[u8]| -> Result<String, String> {
+        match &schema {
+            Some((schema, type_name)) => {
+                let message =
+                    proto_schema::decode(schema, type_name, bytes).map_err(|e| e.to_string())?;
+                Ok(proto_schema::render(schema, &message))
+            }
+            None => render::render(bytes).map_err(|e| e.to_string()),
+        }
+    };
+
+    let Some(delimited) = args.delimited else {
+        print!(
+            "{}",
+            render_message(&bytes).unwrap_or_else(|e| decode_failed(&e))
+        );
+        return;
+    };
+
+    let mut frames = Frames::new(&bytes).max_message_size(delimited.max_message_size);
+    match delimited.action {
+        DelimitedAction::RenderAll => {
+            for (i, frame) in frames.enumerate() {
+                let frame = frame.unwrap_or_else(|e| stream_failed(e));
+                let rendered = render_message(frame)
+                    .unwrap_or_else(|e| decode_failed(&format!("record {i}: {e}")));
+                // A comment, as far as protobuf's text format is concerned
+                println!("# record {i}");
+                print!("{rendered}");
+            }
+        }
+        DelimitedAction::Count => {
+            let count = frames
+                .try_fold(0, |count, frame| frame.map(|_| count + 1))
+                .unwrap_or_else(|e| stream_failed(e));
+            println!("{count}");
+        }
+        DelimitedAction::Render(n) | DelimitedAction::Extract(n) => {
+            let frame = match frames.nth(n) {
+                Some(frame) => frame.unwrap_or_else(|e| stream_failed(e)),
+                None => {
+                    eprintln!("There is no record {n}; records are numbered from 0");
+                    std::process::exit(1);
+                }
+            };
+            if delimited.action == DelimitedAction::Extract(n) {
+                io::stdout()
+                    .write_all(frame)
+                    .expect("Failed to write to stdout");
+            } else {
+                print!(
+                    "{}",
+                    render_message(frame).unwrap_or_else(|e| decode_failed(&e))
+                );
+            }
+        }
+    }
+}
+
+fn decode_failed(msg: &str) -> ! {
+    eprintln!("Failed to decode message: {msg}");
+    std::process::exit(1);
+}
+
+fn stream_failed(e: FrameError) -> ! {
+    eprintln!("Failed to split length-delimited stream: {e}");
+    std::process::exit(1);
+}
+
+fn strip_whitespace(input: &[u8]) -> Vec<u8> {
+    input
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect()
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        input: InputFormat::Hex,
+        path: None,
+        schema: None,
+        delimited: None,
+    };
+    let mut proto_path = None;
+    let mut type_name = None;
+    let mut delimited = false;
+    let mut max_message_size = None;
+    let mut count = false;
+    let mut record = None;
+    let mut extract = false;
+    let mut raw_args = env::args().skip(1);
+
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--input" => {
+                args.input = match raw_args.next().as_deref() {
+                    Some("hex") => InputFormat::Hex,
+                    Some("base64") => InputFormat::Base64,
+                    Some("raw") => InputFormat::Raw,
+                    _ => usage_error("--input must be one of hex, base64 or raw"),
+                }
+            }
+            "--proto" => {
+                proto_path = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--proto must be given a file")),
+                )
+            }
+            "--type" => {
+                type_name = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--type must be given a message name")),
+                )
+            }
+            "--delimited" => delimited = true,
+            "--max-size" => {
+                max_message_size = Some(
+                    raw_args
+                        .next()
+                        .and_then(|size| size.parse().ok())
+                        .unwrap_or_else(|| usage_error("--max-size must be given a byte count")),
+                )
+            }
+            "--count" => count = true,
+            "--record" => {
+                record = Some(
+                    raw_args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or_else(|| usage_error("--record must be given a record number")),
+                )
+            }
+            "--extract" => extract = true,
+            _ if arg.starts_with('-') => usage_error(&format!("Unrecognized argument '{arg}'")),
+            _ if args.path.is_none() => args.path = Some(arg),
+            _ => usage_error("Only one file may be given"),
+        }
+    }
+
+    args.schema = match (proto_path, type_name) {
+        (Some(proto_path), Some(type_name)) => Some((proto_path, type_name)),
+        (None, None) => None,
+        _ => usage_error("--proto and --type must be given together"),
+    };
+
+    if !delimited && (max_message_size.is_some() || count || record.is_some() || extract) {
+        usage_error("--max-size, --count, --record and --extract only apply with --delimited");
+    }
+    if delimited {
+        let action = match (count, record, extract) {
+            (false, None, false) => DelimitedAction::RenderAll,
+            (true, None, false) => DelimitedAction::Count,
+            (false, Some(n), false) => DelimitedAction::Render(n),
+            (false, Some(n), true) => DelimitedAction::Extract(n),
+            (_, _, true) if record.is_none() => usage_error("--extract needs a --record"),
+            _ => usage_error("--count can't be combined with --record"),
+        };
+        args.delimited = Some(Delimited {
+            max_message_size: max_message_size.unwrap_or(DEFAULT_MAX_MESSAGE_SIZE),
+            action,
+        });
+    }
+    args
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
+}
@@ -0,0 +1,233 @@
+use proto_schema::escape_bytes;
+use std::fmt::Write;
+use varint::wire::{self, Field, Value, WireError};
+
+// Same recursion limit protoc uses. Every level of nesting needs at least two bytes (a tag and
+// a length), so a large enough message could otherwise blow our stack.
+const MAX_DEPTH: usize = 100;
+const INDENT: &str = "  ";
+
+#[derive(Debug, PartialEq)]
+pub enum RenderError {
+    Wire(WireError),
+    /// An EGROUP didn't match the most recent SGROUP, or an SGROUP was never closed.
+    UnbalancedGroup {
+        field_number: u32,
+    },
+    /// Groups were nested more than MAX_DEPTH deep.
+    TooDeep,
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Wire(e) => write!(f, "{e}"),
+            RenderError::UnbalancedGroup { field_number } => {
+                write!(f, "unbalanced group for field {field_number}")
+            }
+            RenderError::TooDeep => write!(f, "groups nested more than {MAX_DEPTH} deep"),
+        }
+    }
+}
+
+/// Render a protobuf message without a schema, in the same format as `protoc --decode_raw`:
+/// one `field_number: value` line per record, with nested messages and groups indented
+/// inside braces.
+pub fn render(bytes: &[u8]) -> Result<String, RenderError> {
+    let mut out = String::new();
+    render_message(bytes, 0, &mut out)?;
+    Ok(out)
+}
+
+fn render_message(bytes: &[u8], depth: usize, out: &mut String) -> Result<(), RenderError> {
+    let fields = wire::parse(bytes).map_err(RenderError::Wire)?;
+    render_fields(&mut fields.into_iter(), depth, None, out)
+}
+
+/// Render fields until we run out, or until we find the EGROUP that closes `open_group`.
+fn render_fields<'a>(
+    fields: &mut impl Iterator<Item = Field<'a>>,
+    depth: usize,
+    open_group: Option<u32>,
+    out: &mut String,
+) -> Result<(), RenderError> {
+    let indent = INDENT.repeat(depth);
+
+    while let Some(Field {
+        field_number,
+        value,
+        ..
+    }) = fields.next()
+    {
+        match value {
+            Value::Varint(n) => writeln!(out, "{indent}{field_number}: {n}").unwrap(),
+            Value::I64(n) => writeln!(out, "{indent}{field_number}: 0x{n:016x}").unwrap(),
+            Value::I32(n) => writeln!(out, "{indent}{field_number}: 0x{n:08x}").unwrap(),
+            Value::Len(bytes) => render_len(field_number, bytes, depth, out),
+            // Unlike a LEN that's nested too deep, which we can still show as bytes, there's
+            // nothing else a group could be, so this is an error.
+            Value::SGroup if depth >= MAX_DEPTH => return Err(RenderError::TooDeep),
+            Value::SGroup => {
+                writeln!(out, "{indent}{field_number} {{").unwrap();
+                render_fields(fields, depth + 1, Some(field_number), out)?;
+                writeln!(out, "{indent}}}").unwrap();
+            }
+            Value::EGroup if open_group == Some(field_number) => return Ok(()),
+            Value::EGroup => return Err(RenderError::UnbalancedGroup { field_number }),
+        }
+    }
+
+    match open_group {
+        Some(field_number) => Err(RenderError::UnbalancedGroup { field_number }),
+        None => Ok(()),
+    }
+}
+
+/// Without a schema, a LEN record could be a nested message, a string, packed repeated
+/// varints or plain bytes. We try each in that order (the same order protoc tries them,
+/// plus packed varints), falling back to the next whenever the bytes don't make sense.
+fn render_len(field_number: u32, bytes: &[u8], depth: usize, out: &mut String) {
+    let indent = INDENT.repeat(depth);
+
+    // An empty LEN would be a valid (empty) message, but it's much more likely to be an
+    // empty string.
+    if !bytes.is_empty() && depth < MAX_DEPTH {
+        let mut nested = String::new();
+        if render_message(bytes, depth + 1, &mut nested).is_ok() {
+            writeln!(out, "{indent}{field_number} {{").unwrap();
+            out.push_str(&nested);
+            writeln!(out, "{indent}}}").unwrap();
+            return;
+        }
+    }
+
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        if !s.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+            writeln!(
+                out,
+                "{indent}{field_number}: \"{}\"",
+                escape_bytes(s.as_bytes())
+            )
+            .unwrap();
+            return;
+        }
+    }
+
+    if let Some(packed) = parse_packed_varints(bytes) {
+        let joined = packed
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "{indent}{field_number}: [{joined}]").unwrap();
+        return;
+    }
+
+    writeln!(out, "{indent}{field_number}: \"{}\"", escape_bytes(bytes)).unwrap();
+}
+
+fn parse_packed_varints(bytes: &[u8]) -> Option<Vec<u64>> {
+    let mut packed = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let (n, num_bytes) = varint::try_decode(&bytes[offset..]).ok()?;
+        packed.push(n);
+        offset += num_bytes;
+    }
+    Some(packed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalars() {
+        let bytes = [
+            0x08, 0x96, 0x01, // field 1, VARINT 150
+            0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f, // field 2, I64 (1.0f64)
+            0x1d, 0x00, 0x00, 0x80, 0x3f, // field 3, I32 (1.0f32)
+        ];
+        assert_eq!(
+            render(&bytes).unwrap(),
+            "1: 150\n2: 0x3ff0000000000000\n3: 0x3f800000\n"
+        );
+    }
+
+    #[test]
+    fn string() {
+        let bytes = [0x12, 0x07, 0x74, 0x65, 0x73, 0x74, 0x69, 0x6e, 0x67];
+        assert_eq!(render(&bytes).unwrap(), "2: \"testing\"\n");
+    }
+
+    #[test]
+    fn string_escapes() {
+        // Escaped just as with a schema, so "café" has its 'é' as two octal escapes
+        let s = "it's\t\"café\"";
+        let mut bytes = vec![0x12, s.len() as u8];
+        bytes.extend_from_slice(s.as_bytes());
+        assert_eq!(
+            render(&bytes).unwrap(),
+            "2: \"it\\'s\\t\\\"caf\\303\\251\\\"\"\n"
+        );
+    }
+
+    #[test]
+    fn empty_len() {
+        assert_eq!(render(&[0x12, 0x00]).unwrap(), "2: \"\"\n");
+    }
+
+    #[test]
+    fn nested_message() {
+        let bytes = [0x1a, 0x05, 0x08, 0x96, 0x01, 0x10, 0x02];
+        assert_eq!(render(&bytes).unwrap(), "3 {\n  1: 150\n  2: 2\n}\n");
+    }
+
+    #[test]
+    fn packed_varints() {
+        // field 4 containing the varints 3, 270 and 86942; not a valid message since 0x03
+        // would be a tag with field number 0
+        let bytes = [0x22, 0x06, 0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05];
+        assert_eq!(render(&bytes).unwrap(), "4: [3, 270, 86942]\n");
+    }
+
+    #[test]
+    fn bytes() {
+        // ends in a continuation bit, so it can't be packed varints either
+        let bytes = [0x2a, 0x03, 0x00, 0x41, 0xff];
+        assert_eq!(render(&bytes).unwrap(), "5: \"\\000A\\377\"\n");
+    }
+
+    #[test]
+    fn group() {
+        let bytes = [0x0b, 0x10, 0x01, 0x0c, 0x18, 0x02];
+        assert_eq!(render(&bytes).unwrap(), "1 {\n  2: 1\n}\n3: 2\n");
+    }
+
+    #[test]
+    fn unbalanced_group() {
+        assert_eq!(
+            render(&[0x0b, 0x10, 0x01]),
+            Err(RenderError::UnbalancedGroup { field_number: 1 })
+        );
+        assert_eq!(
+            render(&[0x0c]),
+            Err(RenderError::UnbalancedGroup { field_number: 1 })
+        );
+    }
+
+    #[test]
+    fn deeply_nested_groups() {
+        // Enough SGROUPs to overflow the stack, if we didn't stop at MAX_DEPTH
+        assert_eq!(render(&[0x0b; 200_000]), Err(RenderError::TooDeep));
+
+        let mut just_deep_enough = vec![0x0b; MAX_DEPTH];
+        just_deep_enough.extend([0x0c; MAX_DEPTH]);
+        assert!(render(&just_deep_enough).is_ok());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(render(&[0x08]), Err(RenderError::Wire(_))));
+    }
+}
@@ -1,16 +1,157 @@
-use hex;
-use std::io;
-use varint;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::env;
+use std::io::{self, BufRead, Read, Write};
+use varint::breakdown::Breakdown;
 
 const INPUT_ERROR_MSG: &str = "please provide a UTF-8-encoded hexadecimal string via stdin";
+const BASE64_INPUT_ERROR_MSG: &str = "please provide a base64-encoded string via stdin";
+const USAGE: &str = "Usage: decode_varint [--signed] [--batch] [--concatenated] \
+    [--input hex|base64|raw] [--format decimal|breakdown]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputFormat {
+    Hex,
+    Base64,
+    Raw,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Decimal,
+    Breakdown,
+}
+
+struct Args {
+    // With --signed, we ZigZag-decode the varint the way protobuf does for sint32/sint64 fields.
+    signed: bool,
+    // With --batch, we decode every line of stdin rather than just the first.
+    batch: bool,
+    // With --concatenated, each input may hold many back-to-back varints, and we decode them
+    // all rather than stopping after the first.
+    concatenated: bool,
+    input: InputFormat,
+    format: OutputFormat,
+}
 
 fn main() {
-    let mut unparsed_bytes = String::new();
-    io::stdin()
-        .read_line(&mut unparsed_bytes)
-        .expect(INPUT_ERROR_MSG);
-    let bytes = hex::decode(unparsed_bytes.trim()).expect(INPUT_ERROR_MSG);
-
-    let n = varint::decode(&bytes);
-    println!("{n}");
+    let args = parse_args();
+    let mut stdout = io::stdout().lock();
+
+    for bytes in read_inputs(&args) {
+        if args.concatenated && bytes.is_empty() {
+            continue; // zero varints is a perfectly good concatenation
+        }
+        let mut offset = 0;
+        loop {
+            let remaining = &bytes[offset..];
+            let result = if args.signed {
+                varint::try_decode_signed(remaining).map(|(n, len)| (n.to_string(), len))
+            } else {
+                varint::try_decode(remaining).map(|(n, len)| (n.to_string(), len))
+            };
+
+            let (n, num_bytes) = match result {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    eprintln!("Failed to decode varint: {e}");
+                    std::process::exit(1);
+                }
+            };
+            let encoded = &remaining[..num_bytes];
+            offset += num_bytes;
+
+            let write_result = match args.format {
+                OutputFormat::Decimal => writeln!(stdout, "{n}"),
+                OutputFormat::Breakdown => writeln!(
+                    stdout,
+                    "{} => {n}\n{}",
+                    hex::encode(encoded),
+                    Breakdown(encoded)
+                ),
+            };
+            write_result.expect("Failed to write to stdout");
+
+            if !args.concatenated || offset == bytes.len() {
+                break;
+            }
+        }
+    }
+
+    stdout.flush().expect("Failed to flush stdout");
+}
+
+/// Returns each chunk of bytes that we were given to decode. Raw input is always one chunk
+/// (all of stdin), since there are no lines to split it on; otherwise we decode the first
+/// line, or every line in batch mode.
+fn read_inputs(args: &Args) -> Vec<Vec<u8>> {
+    if args.input == InputFormat::Raw {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .expect("Failed to read from stdin");
+        return vec![bytes];
+    }
+
+    let lines: Vec<String> = io::stdin()
+        .lock()
+        .lines()
+        .map(|line| line.expect(INPUT_ERROR_MSG))
+        .take(if args.batch { usize::MAX } else { 1 })
+        .filter(|line| !args.batch || !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() && !args.batch {
+        panic!("{INPUT_ERROR_MSG}");
+    }
+
+    lines
+        .iter()
+        .map(|line| match args.input {
+            InputFormat::Hex => hex::decode(line.trim()).expect(INPUT_ERROR_MSG),
+            InputFormat::Base64 => BASE64_STANDARD
+                .decode(line.trim())
+                .expect(BASE64_INPUT_ERROR_MSG),
+            InputFormat::Raw => unreachable!(),
+        })
+        .collect()
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        signed: false,
+        batch: false,
+        concatenated: false,
+        input: InputFormat::Hex,
+        format: OutputFormat::Decimal,
+    };
+    let mut raw_args = env::args().skip(1);
+
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--signed" => args.signed = true,
+            "--batch" => args.batch = true,
+            "--concatenated" => args.concatenated = true,
+            "--input" => {
+                args.input = match raw_args.next().as_deref() {
+                    Some("hex") => InputFormat::Hex,
+                    Some("base64") => InputFormat::Base64,
+                    Some("raw") => InputFormat::Raw,
+                    _ => usage_error("--input must be one of hex, base64 or raw"),
+                }
+            }
+            "--format" => {
+                args.format = match raw_args.next().as_deref() {
+                    Some("decimal") => OutputFormat::Decimal,
+                    Some("breakdown") => OutputFormat::Breakdown,
+                    _ => usage_error("--format must be one of decimal or breakdown"),
+                }
+            }
+            _ => usage_error(&format!("Unrecognized argument '{arg}'")),
+        }
+    }
+    args
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
 }
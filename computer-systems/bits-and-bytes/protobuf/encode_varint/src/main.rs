@@ -1,20 +1,111 @@
-use hex;
-use std::io;
-use varint;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::env;
+use std::io::{self, BufRead, Write};
+use varint::breakdown::Breakdown;
 
 const INPUT_ERROR_MSG: &str = "please provide an unsigned 64-bit integer to encode via stdin";
+const SIGNED_INPUT_ERROR_MSG: &str = "please provide a signed 64-bit integer to encode via stdin";
+const USAGE: &str = "Usage: encode_varint [--signed] [--batch] [--format hex|raw|base64|breakdown]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Hex,
+    Raw,
+    Base64,
+    Breakdown,
+}
+
+struct Args {
+    // With --signed, we ZigZag-encode the input the way protobuf does for sint32/sint64 fields.
+    signed: bool,
+    // With --batch, we encode every line of stdin rather than just the first.
+    batch: bool,
+    format: OutputFormat,
+}
 
 fn main() {
-    let mut unparsed_n = String::new();
-    io::stdin()
-        .read_line(&mut unparsed_n)
-        .expect(INPUT_ERROR_MSG);
-    let n = unparsed_n.trim().parse::<u64>().expect(INPUT_ERROR_MSG);
+    let args = parse_args();
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    let mut lines = stdin.lock().lines();
     let mut bytes: Vec<u8> = vec![0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
 
-    let num_bytes = varint::encode(n, &mut bytes);
+    loop {
+        let unparsed_n = match lines.next() {
+            Some(line) => line.expect(INPUT_ERROR_MSG),
+            None if args.batch => break,
+            None => panic!("{INPUT_ERROR_MSG}"),
+        };
+        if args.batch && unparsed_n.trim().is_empty() {
+            continue;
+        }
+
+        let num_bytes = if args.signed {
+            let n = unparsed_n
+                .trim()
+                .parse::<i64>()
+                .expect(SIGNED_INPUT_ERROR_MSG);
+            varint::encode_signed(n, &mut bytes)
+        } else {
+            let n = unparsed_n.trim().parse::<u64>().expect(INPUT_ERROR_MSG);
+            varint::encode(n, &mut bytes)
+        };
+        let encoded = &bytes[0..num_bytes];
+
+        // By default we output bytes to stdout as UTF-8 formatted hexadecimal (terminated by
+        // a newline). Raw output has no separators at all, so a batch of raw varints comes out
+        // as one concatenated stream.
+        let write_result = match args.format {
+            OutputFormat::Hex => writeln!(stdout, "{}", hex::encode(encoded)),
+            OutputFormat::Raw => stdout.write_all(encoded),
+            OutputFormat::Base64 => writeln!(stdout, "{}", BASE64_STANDARD.encode(encoded)),
+            OutputFormat::Breakdown => writeln!(
+                stdout,
+                "{} => {}\n{}",
+                unparsed_n.trim(),
+                hex::encode(encoded),
+                Breakdown(encoded)
+            ),
+        };
+        write_result.expect("Failed to write to stdout");
+
+        if !args.batch {
+            break;
+        }
+    }
+
+    stdout.flush().expect("Failed to flush stdout");
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        signed: false,
+        batch: false,
+        format: OutputFormat::Hex,
+    };
+    let mut raw_args = env::args().skip(1);
+
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--signed" => args.signed = true,
+            "--batch" => args.batch = true,
+            "--format" => {
+                args.format = match raw_args.next().as_deref() {
+                    Some("hex") => OutputFormat::Hex,
+                    Some("raw") => OutputFormat::Raw,
+                    Some("base64") => OutputFormat::Base64,
+                    Some("breakdown") => OutputFormat::Breakdown,
+                    _ => usage_error("--format must be one of hex, raw, base64 or breakdown"),
+                }
+            }
+            _ => usage_error(&format!("Unrecognized argument '{arg}'")),
+        }
+    }
+    args
+}
 
-    // we output bytes to stdout as UTF-8 formatted hexadecimal (terminated by a newline)
-    let utf8_encoded_hex = hex::encode(&bytes[0..num_bytes]);
-    println!("{utf8_encoded_hex}");
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
 }
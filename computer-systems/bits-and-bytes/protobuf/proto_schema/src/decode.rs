@@ -0,0 +1,287 @@
+use super::{Field, FieldType, MessageType, Schema};
+
+use std::fmt;
+use varint::wire::{self, WireError, WireType};
+
+// Same recursion limit protoc uses, as in decode_message's schemaless renderer.
+const MAX_DEPTH: usize = 100;
+
+/// A message decoded with the help of its schema. Fields are kept in the order they appeared
+/// on the wire; every element of a repeated field (packed or not) is its own `DecodedField`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedMessage {
+    pub type_name: String,
+    pub fields: Vec<DecodedField>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedField {
+    pub number: u32,
+    pub value: Value,
+}
+
+/// A field value, interpreted according to its declared type. The sint, fixed and sfixed
+/// types decode to the same variants as their plain int counterparts, since once they're off
+/// the wire there's no difference in what they mean.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int32(i32),
+    Int64(i64),
+    Uint32(u32),
+    Uint64(u64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+    /// An enum's number, which may or may not be one the schema gives a name to.
+    Enum(i32),
+    Message(DecodedMessage),
+    /// A field the schema doesn't know about. We keep its raw value, like protoc does.
+    Unknown(UnknownValue),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnknownValue {
+    Varint(u64),
+    I64(u64),
+    Len(Vec<u8>),
+    I32(u32),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MessageDecodeError {
+    Wire(WireError),
+    UnknownMessageType(String),
+    /// A field's wire type doesn't match what its declared type is encoded as.
+    WireTypeMismatch {
+        field: String,
+        expected: WireType,
+        found: WireType,
+    },
+    InvalidUtf8 {
+        field: String,
+    },
+    /// A packed repeated field whose payload isn't a whole number of elements.
+    InvalidPacked {
+        field: String,
+    },
+    /// Groups are deprecated and can't be declared in proto3, so we don't support them.
+    Group {
+        field_number: u32,
+    },
+    TooDeep,
+}
+
+impl fmt::Display for MessageDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MessageDecodeError::*;
+        match self {
+            Wire(e) => write!(f, "{e}"),
+            UnknownMessageType(name) => write!(f, "no message type named {name} in the schema"),
+            WireTypeMismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field {field} should have wire type {expected:?} but has {found:?}"
+            ),
+            InvalidUtf8 { field } => write!(f, "string field {field} is not valid UTF-8"),
+            InvalidPacked { field } => {
+                write!(f, "packed field {field} has a partial element at the end")
+            }
+            Group { field_number } => {
+                write!(f, "field {field_number} is a group, which isn't supported")
+            }
+            TooDeep => write!(f, "messages are nested more than {MAX_DEPTH} levels deep"),
+        }
+    }
+}
+
+impl std::error::Error for MessageDecodeError {}
+
+/// Decode a message of the given type (a fully-qualified name, like `pkg.Msg`).
+pub fn decode(
+    schema: &Schema,
+    type_name: &str,
+    bytes: &[u8],
+) -> Result<DecodedMessage, MessageDecodeError> {
+    let message_type = schema
+        .message(type_name)
+        .ok_or_else(|| MessageDecodeError::UnknownMessageType(type_name.to_string()))?;
+    decode_message(schema, message_type, bytes, 0)
+}
+
+fn decode_message(
+    schema: &Schema,
+    message_type: &MessageType,
+    bytes: &[u8],
+    depth: usize,
+) -> Result<DecodedMessage, MessageDecodeError> {
+    if depth > MAX_DEPTH {
+        return Err(MessageDecodeError::TooDeep);
+    }
+
+    let mut fields = Vec::new();
+    for record in wire::Fields::new(bytes) {
+        let record = record.map_err(MessageDecodeError::Wire)?;
+        let number = record.field_number;
+
+        let Some(field) = message_type.field_by_number(number) else {
+            let value = match record.value {
+                wire::Value::Varint(n) => UnknownValue::Varint(n),
+                wire::Value::I64(n) => UnknownValue::I64(n),
+                wire::Value::Len(bytes) => UnknownValue::Len(bytes.to_vec()),
+                wire::Value::I32(n) => UnknownValue::I32(n),
+                wire::Value::SGroup | wire::Value::EGroup => {
+                    return Err(MessageDecodeError::Group {
+                        field_number: number,
+                    })
+                }
+            };
+            fields.push(DecodedField {
+                number,
+                value: Value::Unknown(value),
+            });
+            continue;
+        };
+
+        let expected = wire_type_of(&field.field_type);
+        match record.value {
+            // Parsers have to accept packed and unpacked encodings of any packable repeated
+            // field, whichever one the schema asks for.
+            wire::Value::Len(payload)
+                if expected != WireType::Len
+                    && field.repeated
+                    && field.field_type.is_packable() =>
+            {
+                for value in decode_packed(field, expected, payload)? {
+                    fields.push(DecodedField { number, value });
+                }
+            }
+            value if record.wire_type == expected => fields.push(DecodedField {
+                number,
+                value: decode_value(schema, field, value, depth)?,
+            }),
+            _ => {
+                return Err(MessageDecodeError::WireTypeMismatch {
+                    field: field.name.clone(),
+                    expected,
+                    found: record.wire_type,
+                })
+            }
+        }
+    }
+
+    Ok(DecodedMessage {
+        type_name: message_type.full_name.clone(),
+        fields,
+    })
+}
+
+/// The wire type every non-packed value of the given type is encoded with.
+pub(crate) fn wire_type_of(field_type: &FieldType) -> WireType {
+    use FieldType::*;
+    match field_type {
+        Int32 | Int64 | Uint32 | Uint64 | Sint32 | Sint64 | Bool | Enum(_) => WireType::Varint,
+        Fixed64 | Sfixed64 | Double => WireType::I64,
+        Fixed32 | Sfixed32 | Float => WireType::I32,
+        String | Bytes | Message(_) => WireType::Len,
+    }
+}
+
+fn decode_value(
+    schema: &Schema,
+    field: &Field,
+    value: wire::Value,
+    depth: usize,
+) -> Result<Value, MessageDecodeError> {
+    Ok(match value {
+        wire::Value::Varint(n) => decode_varint(&field.field_type, n),
+        wire::Value::I64(n) => decode_i64(&field.field_type, n),
+        wire::Value::I32(n) => decode_i32(&field.field_type, n),
+        wire::Value::Len(bytes) => match &field.field_type {
+            FieldType::String => match std::str::from_utf8(bytes) {
+                Ok(s) => Value::String(s.to_string()),
+                Err(_) => {
+                    return Err(MessageDecodeError::InvalidUtf8 {
+                        field: field.name.clone(),
+                    })
+                }
+            },
+            FieldType::Message(type_name) => {
+                // The parser only ever resolves type names to messages that exist
+                let message_type = schema.message(type_name).unwrap();
+                Value::Message(decode_message(schema, message_type, bytes, depth + 1)?)
+            }
+            _ => Value::Bytes(bytes.to_vec()),
+        },
+        wire::Value::SGroup | wire::Value::EGroup => unreachable!("no field type uses groups"),
+    })
+}
+
+fn decode_varint(field_type: &FieldType, n: u64) -> Value {
+    // The 32-bit types are truncated rather than rejected when the varint is too large, which
+    // is what the protobuf spec says parsers should do (it's how an int64 field can be
+    // changed to an int32 without breaking old writers).
+    match field_type {
+        FieldType::Int32 => Value::Int32(n as i32),
+        FieldType::Int64 => Value::Int64(n as i64),
+        FieldType::Uint32 => Value::Uint32(n as u32),
+        FieldType::Sint32 => Value::Int32(varint::zigzag::decode_i32(n as u32)),
+        FieldType::Sint64 => Value::Int64(varint::zigzag::decode_i64(n)),
+        FieldType::Bool => Value::Bool(n != 0),
+        FieldType::Enum(_) => Value::Enum(n as i32),
+        _ => Value::Uint64(n),
+    }
+}
+
+fn decode_i64(field_type: &FieldType, n: u64) -> Value {
+    match field_type {
+        FieldType::Double => Value::Double(f64::from_bits(n)),
+        FieldType::Sfixed64 => Value::Int64(n as i64),
+        _ => Value::Uint64(n),
+    }
+}
+
+fn decode_i32(field_type: &FieldType, n: u32) -> Value {
+    match field_type {
+        FieldType::Float => Value::Float(f32::from_bits(n)),
+        FieldType::Sfixed32 => Value::Int32(n as i32),
+        _ => Value::Uint32(n),
+    }
+}
+
+fn decode_packed(
+    field: &Field,
+    element_wire_type: WireType,
+    mut payload: &[u8],
+) -> Result<Vec<Value>, MessageDecodeError> {
+    let invalid = || MessageDecodeError::InvalidPacked {
+        field: field.name.clone(),
+    };
+
+    let mut values = Vec::new();
+    while !payload.is_empty() {
+        let (value, num_bytes) = match element_wire_type {
+            WireType::Varint => {
+                let (n, num_bytes) = varint::try_decode(payload).map_err(|_| invalid())?;
+                (decode_varint(&field.field_type, n), num_bytes)
+            }
+            WireType::I64 => {
+                let bytes = payload.get(..8).ok_or_else(invalid)?;
+                let n = u64::from_le_bytes(bytes.try_into().unwrap());
+                (decode_i64(&field.field_type, n), 8)
+            }
+            _ => {
+                let bytes = payload.get(..4).ok_or_else(invalid)?;
+                let n = u32::from_le_bytes(bytes.try_into().unwrap());
+                (decode_i32(&field.field_type, n), 4)
+            }
+        };
+        values.push(value);
+        payload = &payload[num_bytes..];
+    }
+    Ok(values)
+}
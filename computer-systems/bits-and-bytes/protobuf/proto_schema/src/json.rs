@@ -0,0 +1,488 @@
+use super::{DecodedMessage, Field, FieldType, MessageType, Schema, Value};
+
+use base64::prelude::{Engine, BASE64_STANDARD, BASE64_STANDARD_NO_PAD};
+use serde_json::{Map, Number};
+use std::collections::HashSet;
+use std::fmt;
+use varint::writer::MessageWriter;
+
+// Same recursion limit as decoding, since JSON objects can nest just as deeply.
+const MAX_DEPTH: usize = 100;
+
+#[derive(Debug, PartialEq)]
+pub enum JsonError {
+    UnknownMessageType(String),
+    /// An object had a key that isn't the JSON name or the original name of any field.
+    UnknownField {
+        type_name: String,
+        key: String,
+    },
+    /// A value had the wrong JSON type for its field, or couldn't be parsed as one.
+    InvalidValue {
+        field: String,
+        expected: &'static str,
+    },
+    /// An object had a field under both its JSON name and its original name.
+    DuplicateField {
+        type_name: String,
+        field: String,
+    },
+    TooDeep,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use JsonError::*;
+        match self {
+            UnknownMessageType(name) => write!(f, "no message type named {name} in the schema"),
+            UnknownField { type_name, key } => write!(f, "{type_name} has no field named {key:?}"),
+            InvalidValue { field, expected } => write!(f, "field {field} should be {expected}"),
+            DuplicateField { type_name, field } => {
+                write!(f, "{type_name} has field {field} more than once")
+            }
+            TooDeep => write!(f, "messages are nested more than {MAX_DEPTH} levels deep"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Convert a decoded message to JSON, following the canonical proto3 mapping from
+/// https://protobuf.dev/programming-guides/json/. Fields are keyed by their lowerCamelCase
+/// JSON names, in field number order. 64-bit integers become strings (since JSON numbers are
+/// usually doubles, which can't hold them exactly), bytes become base64, and enums become
+/// their value names. Fields the schema doesn't know about are dropped, as there's nowhere to
+/// put them in JSON.
+///
+/// Only the fields present on the wire are written out. Proto3 writers leave out fields set
+/// to their default value, so this matches the canonical mapping's default of omitting them.
+///
+/// Panics if the message's type isn't in the schema, which can't happen for a message that
+/// came from `decode`.
+pub fn to_json(schema: &Schema, message: &DecodedMessage) -> serde_json::Value {
+    let message_type = schema
+        .message(&message.type_name)
+        .unwrap_or_else(|| panic!("no message type named {} in the schema", message.type_name));
+
+    let mut fields: Vec<&Field> = message_type.fields.iter().collect();
+    fields.sort_by_key(|field| field.number);
+
+    let mut object = Map::new();
+    for field in fields {
+        let values: Vec<&Value> = message
+            .fields
+            .iter()
+            .filter(|decoded| decoded.number == field.number)
+            .map(|decoded| &decoded.value)
+            .collect();
+        let Some(last) = values.last() else {
+            continue;
+        };
+
+        let json = match &field.field_type {
+            FieldType::Message(type_name) if is_map(schema, type_name) => {
+                let entries = values.iter().map(|value| map_entry_to_json(schema, value));
+                serde_json::Value::Object(entries.collect())
+            }
+            field_type if field.repeated => serde_json::Value::Array(
+                values
+                    .iter()
+                    .map(|value| value_to_json(schema, field_type, value))
+                    .collect(),
+            ),
+            // A singular field that shows up more than once takes its last value
+            field_type => value_to_json(schema, field_type, last),
+        };
+        object.insert(json_name(&field.name), json);
+    }
+    serde_json::Value::Object(object)
+}
+
+fn is_map(schema: &Schema, type_name: &str) -> bool {
+    schema
+        .message(type_name)
+        .is_some_and(|message_type| message_type.map_entry)
+}
+
+/// Map entries become `"key": value` pairs. JSON keys are always strings, so every key type
+/// (integers and bools included) is written in its string form.
+fn map_entry_to_json(schema: &Schema, entry: &Value) -> (String, serde_json::Value) {
+    let Value::Message(entry) = entry else {
+        unreachable!("map entries are always messages")
+    };
+    let entry_type = schema.message(&entry.type_name).unwrap();
+    let find = |number| {
+        let field = entry_type.field_by_number(number).unwrap();
+        let value = entry
+            .fields
+            .iter()
+            .rev()
+            .find(|decoded| decoded.number == number)
+            .map(|decoded| value_to_json(schema, &field.field_type, &decoded.value));
+        // A key or value left out on the wire has its type's default value
+        value.unwrap_or_else(|| default_json(schema, &field.field_type))
+    };
+
+    let key = match find(1) {
+        serde_json::Value::String(s) => s,
+        key => key.to_string(),
+    };
+    (key, find(2))
+}
+
+fn value_to_json(schema: &Schema, field_type: &FieldType, value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match value {
+        Value::Int32(n) => Json::from(*n),
+        Value::Uint32(n) => Json::from(*n),
+        Value::Int64(n) => Json::String(n.to_string()),
+        Value::Uint64(n) => Json::String(n.to_string()),
+        // Going through the shortest string that round-trips, so that 1.1f32 doesn't come out
+        // as 1.100000023841858
+        Value::Float(n) => float_to_json(n.to_string().parse().unwrap()),
+        Value::Double(n) => float_to_json(*n),
+        Value::Bool(b) => Json::Bool(*b),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Bytes(bytes) => Json::String(BASE64_STANDARD.encode(bytes)),
+        Value::Enum(n) => {
+            let enum_type = match field_type {
+                FieldType::Enum(enum_name) => schema.enum_type(enum_name),
+                _ => None,
+            };
+            // Numbers the schema has no name for are written as plain numbers
+            match enum_type.and_then(|enum_type| enum_type.name_of(*n)) {
+                Some(name) => Json::String(name.to_string()),
+                None => Json::from(*n),
+            }
+        }
+        Value::Message(nested) => to_json(schema, nested),
+        Value::Unknown(_) => unreachable!("unknown fields are never looked up by field"),
+    }
+}
+
+fn float_to_json(n: f64) -> serde_json::Value {
+    match Number::from_f64(n) {
+        Some(number) => serde_json::Value::Number(number),
+        None if n.is_nan() => serde_json::Value::String("NaN".to_string()),
+        None if n > 0.0 => serde_json::Value::String("Infinity".to_string()),
+        None => serde_json::Value::String("-Infinity".to_string()),
+    }
+}
+
+fn default_json(schema: &Schema, field_type: &FieldType) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match field_type {
+        FieldType::Int64
+        | FieldType::Uint64
+        | FieldType::Sint64
+        | FieldType::Fixed64
+        | FieldType::Sfixed64 => Json::String("0".to_string()),
+        FieldType::Bool => Json::Bool(false),
+        FieldType::String | FieldType::Bytes => Json::String(String::new()),
+        FieldType::Enum(_) => value_to_json(schema, field_type, &Value::Enum(0)),
+        FieldType::Message(_) => Json::Object(Map::new()),
+        _ => Json::from(0),
+    }
+}
+
+/// Convert JSON in the canonical proto3 mapping back to a binary message of the given type.
+/// Keys may be either a field's JSON name or its original name from the `.proto` file, and a
+/// `null` value means the field is absent. Fields are written in field number order, with
+/// repeated scalars packed, which is how protoc would encode the same message.
+pub fn from_json(
+    schema: &Schema,
+    type_name: &str,
+    json: &serde_json::Value,
+) -> Result<Vec<u8>, JsonError> {
+    let message_type = schema
+        .message(type_name)
+        .ok_or_else(|| JsonError::UnknownMessageType(type_name.to_string()))?;
+    let mut writer = MessageWriter::new();
+    write_message(schema, message_type, json, &mut writer, 0)?;
+    Ok(writer.finish())
+}
+
+fn write_message(
+    schema: &Schema,
+    message_type: &MessageType,
+    json: &serde_json::Value,
+    writer: &mut MessageWriter,
+    depth: usize,
+) -> Result<(), JsonError> {
+    if depth > MAX_DEPTH {
+        return Err(JsonError::TooDeep);
+    }
+    let object = json.as_object().ok_or_else(|| JsonError::InvalidValue {
+        field: message_type.full_name.clone(),
+        expected: "an object",
+    })?;
+
+    let mut present = Vec::new();
+    let mut seen = HashSet::new();
+    for (key, value) in object {
+        let field = message_type
+            .fields
+            .iter()
+            .find(|field| json_name(&field.name) == *key || field.name == *key)
+            .ok_or_else(|| JsonError::UnknownField {
+                type_name: message_type.full_name.clone(),
+                key: key.clone(),
+            })?;
+        // Like protobuf's own parser, we reject "fooBar" and "foo_bar" together (even when one
+        // is null) rather than pick one of them.
+        if !seen.insert(field.number) {
+            return Err(JsonError::DuplicateField {
+                type_name: message_type.full_name.clone(),
+                field: field.name.clone(),
+            });
+        }
+        if !value.is_null() {
+            present.push((field, value));
+        }
+    }
+    present.sort_by_key(|(field, _)| field.number);
+
+    for (field, value) in present {
+        write_field(schema, field, value, writer, depth)?;
+    }
+    Ok(())
+}
+
+fn write_field(
+    schema: &Schema,
+    field: &Field,
+    json: &serde_json::Value,
+    writer: &mut MessageWriter,
+    depth: usize,
+) -> Result<(), JsonError> {
+    match &field.field_type {
+        FieldType::Message(type_name) if is_map(schema, type_name) => {
+            let entry_type = schema.message(type_name).unwrap();
+            let key_field = entry_type.field_by_number(1).unwrap();
+            let value_field = entry_type.field_by_number(2).unwrap();
+            let object = json
+                .as_object()
+                .ok_or_else(|| invalid(field, "an object"))?;
+            for (key, value) in object {
+                writer.start_message(field.number);
+                // JSON keys are always strings, whatever type the map's keys really are
+                let key = serde_json::Value::String(key.clone());
+                write_value(schema, key_field, &key, writer, depth + 1)?;
+                write_value(schema, value_field, value, writer, depth + 1)?;
+                writer.end_message();
+            }
+        }
+        field_type if field.repeated => {
+            let array = json.as_array().ok_or_else(|| invalid(field, "an array"))?;
+            if field_type.is_packable() {
+                write_packed(schema, field, array, writer)?;
+            } else {
+                for element in array {
+                    write_value(schema, field, element, writer, depth)?;
+                }
+            }
+        }
+        _ => write_value(schema, field, json, writer, depth)?,
+    }
+    Ok(())
+}
+
+fn write_value(
+    schema: &Schema,
+    field: &Field,
+    json: &serde_json::Value,
+    writer: &mut MessageWriter,
+    depth: usize,
+) -> Result<(), JsonError> {
+    match &field.field_type {
+        FieldType::String => {
+            let s = json.as_str().ok_or_else(|| invalid(field, "a string"))?;
+            writer.string(field.number, s);
+        }
+        FieldType::Bytes => {
+            let bytes = json
+                .as_str()
+                .and_then(decode_base64)
+                .ok_or_else(|| invalid(field, "a base64 string"))?;
+            writer.bytes(field.number, &bytes);
+        }
+        FieldType::Message(type_name) => {
+            let message_type = schema.message(type_name).unwrap();
+            writer.start_message(field.number);
+            write_message(schema, message_type, json, writer, depth + 1)?;
+            writer.end_message();
+        }
+        _ => match scalar(schema, field, json)? {
+            Scalar::Varint(n) => {
+                writer.varint(field.number, n);
+            }
+            Scalar::I64(n) => {
+                writer.fixed64(field.number, n);
+            }
+            Scalar::I32(n) => {
+                writer.fixed32(field.number, n);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn write_packed(
+    schema: &Schema,
+    field: &Field,
+    array: &[serde_json::Value],
+    writer: &mut MessageWriter,
+) -> Result<(), JsonError> {
+    // Like protoc, we leave out an empty packed field entirely
+    if array.is_empty() {
+        return Ok(());
+    }
+
+    let mut varints = Vec::new();
+    let mut fixed = Vec::new();
+    for element in array {
+        match scalar(schema, field, element)? {
+            Scalar::Varint(n) => varints.push(n),
+            Scalar::I64(n) => fixed.extend_from_slice(&n.to_le_bytes()),
+            Scalar::I32(n) => fixed.extend_from_slice(&n.to_le_bytes()),
+        }
+    }
+    if fixed.is_empty() {
+        writer.packed(field.number, &varints);
+    } else {
+        writer.bytes(field.number, &fixed);
+    }
+    Ok(())
+}
+
+/// A scalar value ready to be written, in whichever wire encoding its type uses.
+enum Scalar {
+    Varint(u64),
+    I64(u64),
+    I32(u32),
+}
+
+fn scalar(schema: &Schema, field: &Field, json: &serde_json::Value) -> Result<Scalar, JsonError> {
+    use varint::zigzag;
+    use FieldType::*;
+    Ok(match &field.field_type {
+        // Negative int32s are sign-extended to 64 bits, so they always take ten bytes
+        Int32 => Scalar::Varint(parse_int::<i32>(field, json)? as i64 as u64),
+        Int64 => Scalar::Varint(parse_int::<i64>(field, json)? as u64),
+        Uint32 => Scalar::Varint(parse_int::<u32>(field, json)? as u64),
+        Uint64 => Scalar::Varint(parse_int::<u64>(field, json)?),
+        Sint32 => Scalar::Varint(zigzag::encode_i32(parse_int(field, json)?) as u64),
+        Sint64 => Scalar::Varint(zigzag::encode_i64(parse_int(field, json)?)),
+        Fixed32 => Scalar::I32(parse_int(field, json)?),
+        Sfixed32 => Scalar::I32(parse_int::<i32>(field, json)? as u32),
+        Fixed64 => Scalar::I64(parse_int(field, json)?),
+        Sfixed64 => Scalar::I64(parse_int::<i64>(field, json)? as u64),
+        Float => {
+            let n = parse_float(field, json)?;
+            if n.is_finite() && (n as f32).is_infinite() {
+                return Err(invalid(field, "within the range of a float"));
+            }
+            Scalar::I32((n as f32).to_bits())
+        }
+        Double => Scalar::I64(parse_float(field, json)?.to_bits()),
+        Bool => Scalar::Varint(parse_bool(field, json)? as u64),
+        Enum(enum_name) => {
+            let enum_type = schema.enum_type(enum_name).unwrap();
+            let n = match json {
+                serde_json::Value::String(name) => enum_type
+                    .number_of(name)
+                    .ok_or_else(|| invalid(field, "one of the enum's value names"))?,
+                _ => parse_int::<i32>(field, json)?,
+            };
+            Scalar::Varint(n as i64 as u64)
+        }
+        String | Bytes | Message(_) => unreachable!("not a scalar type"),
+    })
+}
+
+/// Integers can be given as JSON numbers or as strings, whatever their width.
+fn parse_int<T>(field: &Field, json: &serde_json::Value) -> Result<T, JsonError>
+where
+    T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr,
+{
+    let n = match json {
+        serde_json::Value::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                T::try_from(n).ok()
+            } else if let Some(n) = n.as_i64() {
+                T::try_from(n).ok()
+            } else {
+                // Exponent notation like 1e3 is allowed, as long as it comes out whole
+                n.as_f64()
+                    .filter(|f| f.fract() == 0.0 && f.abs() < 2f64.powi(63))
+                    .and_then(|f| T::try_from(f as i64).ok())
+            }
+        }
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    n.ok_or_else(|| invalid(field, "an integer in range"))
+}
+
+fn parse_float(field: &Field, json: &serde_json::Value) -> Result<f64, JsonError> {
+    let n = match json {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            s => s.parse().ok(),
+        },
+        _ => None,
+    };
+    n.ok_or_else(|| invalid(field, "a number"))
+}
+
+fn parse_bool(field: &Field, json: &serde_json::Value) -> Result<bool, JsonError> {
+    match json {
+        serde_json::Value::Bool(b) => Ok(*b),
+        // Only for map keys, which are always strings
+        serde_json::Value::String(s) if s == "true" => Ok(true),
+        serde_json::Value::String(s) if s == "false" => Ok(false),
+        _ => Err(invalid(field, "true or false")),
+    }
+}
+
+/// The canonical mapping writes standard base64 with padding, but parsers have to accept the
+/// URL-safe alphabet too, with or without padding.
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let standard: String = s
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    BASE64_STANDARD_NO_PAD.decode(standard).ok()
+}
+
+fn invalid(field: &Field, expected: &'static str) -> JsonError {
+    JsonError::InvalidValue {
+        field: field.name.clone(),
+        expected,
+    }
+}
+
+/// `field_name` => `fieldName`, the way protoc picks a field's JSON name.
+fn json_name(name: &str) -> String {
+    let mut json_name = String::new();
+    let mut capitalize_next = false;
+    for c in name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            json_name.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
@@ -0,0 +1,136 @@
+mod decode;
+mod json;
+mod parser;
+mod text;
+
+pub use decode::{decode, DecodedField, DecodedMessage, MessageDecodeError, UnknownValue, Value};
+pub use json::{from_json, to_json, JsonError};
+pub use parser::{parse, ParseError};
+pub use text::{escape_bytes, render};
+
+use std::collections::HashMap;
+
+/// Every message and enum type defined in a `.proto` file, keyed by fully-qualified name (the
+/// package, then any enclosing messages, then the type's own name, separated by dots).
+#[derive(Debug, Default)]
+pub struct Schema {
+    pub package: Option<String>,
+    pub messages: HashMap<String, MessageType>,
+    pub enums: HashMap<String, EnumType>,
+}
+
+#[derive(Debug)]
+pub struct MessageType {
+    pub full_name: String,
+    pub fields: Vec<Field>,
+    /// True for the synthetic `<FieldName>Entry` messages that a `map<K, V>` field is made of.
+    /// On the wire, a map is just a repeated message whose `key` is field 1 and `value` is 2.
+    pub map_entry: bool,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: String,
+    pub number: u32,
+    pub field_type: FieldType,
+    pub repeated: bool,
+}
+
+#[derive(Debug)]
+pub struct EnumType {
+    pub full_name: String,
+    pub values: Vec<(String, i32)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+    /// The fully-qualified name of a message type in the schema.
+    Message(String),
+    /// The fully-qualified name of an enum type in the schema.
+    Enum(String),
+}
+
+impl FieldType {
+    fn from_scalar_name(name: &str) -> Option<Self> {
+        use FieldType::*;
+        Some(match name {
+            "double" => Double,
+            "float" => Float,
+            "int32" => Int32,
+            "int64" => Int64,
+            "uint32" => Uint32,
+            "uint64" => Uint64,
+            "sint32" => Sint32,
+            "sint64" => Sint64,
+            "fixed32" => Fixed32,
+            "fixed64" => Fixed64,
+            "sfixed32" => Sfixed32,
+            "sfixed64" => Sfixed64,
+            "bool" => Bool,
+            "string" => String,
+            "bytes" => Bytes,
+            _ => return None,
+        })
+    }
+
+    /// Whether a repeated field of this type can be packed: every scalar numeric type can.
+    pub fn is_packable(&self) -> bool {
+        !matches!(
+            self,
+            FieldType::String | FieldType::Bytes | FieldType::Message(_)
+        )
+    }
+}
+
+impl MessageType {
+    pub fn field_by_number(&self, number: u32) -> Option<&Field> {
+        self.fields.iter().find(|field| field.number == number)
+    }
+
+    pub fn field_by_name(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+impl EnumType {
+    pub fn name_of(&self, number: i32) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(_, value)| *value == number)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn number_of(&self, name: &str) -> Option<i32> {
+        self.values
+            .iter()
+            .find(|(value_name, _)| value_name == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+impl Schema {
+    /// Look up a message type by its fully-qualified name. A leading dot is allowed, as in
+    /// `.pkg.Msg`, but not required.
+    pub fn message(&self, full_name: &str) -> Option<&MessageType> {
+        self.messages.get(full_name.trim_start_matches('.'))
+    }
+
+    pub fn enum_type(&self, full_name: &str) -> Option<&EnumType> {
+        self.enums.get(full_name.trim_start_matches('.'))
+    }
+}
@@ -0,0 +1,620 @@
+use super::{EnumType, Field, FieldType, MessageType, Schema};
+
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse the contents of a proto3 `.proto` file. This covers messages (including nested
+/// ones), scalar fields, enums, `repeated`/`optional` labels, `oneof` and `map<K, V>` fields.
+/// Options, `reserved` statements and services are skipped over. Imports are allowed, but
+/// since we only ever see one file, any type that would come from an import is an error.
+pub fn parse(source: &str) -> Result<Schema, ParseError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        schema: Schema::default(),
+        unresolved_fields: Vec::new(),
+    };
+    parser.parse_file()?;
+    parser.resolve_types()?;
+    Ok(parser.schema)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    // Floats only ever show up in option values, which we skip, so we don't bother parsing them
+    Float,
+    Str(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{s}'"),
+            Token::Int(n) => write!(f, "'{n}'"),
+            Token::Float => write!(f, "a float"),
+            Token::Str(s) => write!(f, "{s:?}"),
+            Token::Symbol(c) => write!(f, "'{c}'"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == '/' {
+            chars.next();
+            match chars.next() {
+                Some('/') => while chars.next_if(|&c| c != '\n').is_some() {},
+                Some('*') => {
+                    let mut prev = ' ';
+                    loop {
+                        match chars.next() {
+                            Some('/') if prev == '*' => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                prev = c;
+                            }
+                            None => return Err(error(line, "unterminated block comment")),
+                        }
+                    }
+                }
+                _ => return Err(error(line, "unexpected '/'")),
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                ident.push(c);
+            }
+            tokens.push((Token::Ident(ident), line));
+        } else if c.is_ascii_digit() {
+            let mut literal = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '.') {
+                literal.push(c);
+            }
+            tokens.push((parse_number(&literal, line)?, line));
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some(end) if end == c => break,
+                    Some('\\') => s.push(chars.next().unwrap_or('\\')),
+                    Some('\n') | None => return Err(error(line, "unterminated string")),
+                    Some(c) => s.push(c),
+                }
+            }
+            tokens.push((Token::Str(s), line));
+        } else if "{}[]()<>=;,.-+".contains(c) {
+            chars.next();
+            tokens.push((Token::Symbol(c), line));
+        } else {
+            return Err(error(line, &format!("unexpected character '{c}'")));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(literal: &str, line: usize) -> Result<Token, ParseError> {
+    let parsed = if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)
+    } else if literal.len() > 1 && literal.starts_with('0') && !literal.contains('.') {
+        i64::from_str_radix(&literal[1..], 8)
+    } else if literal.contains(['.', 'e', 'E']) {
+        return Ok(Token::Float);
+    } else {
+        literal.parse()
+    };
+    parsed
+        .map(Token::Int)
+        .map_err(|_| error(line, &format!("invalid number '{literal}'")))
+}
+
+fn error(line: usize, message: &str) -> ParseError {
+    ParseError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    schema: Schema,
+    // Fields whose type names we can't look up until the whole file has been parsed, since
+    // a type may be used before it's defined: (message full name, field index, type name as
+    // written, the scope it was written in, line).
+    unresolved_fields: Vec<(String, usize, String, String, usize)>,
+}
+
+impl Parser {
+    fn parse_file(&mut self) -> Result<(), ParseError> {
+        while let Some(token) = self.next() {
+            match token {
+                Token::Ident(keyword) => match keyword.as_str() {
+                    "syntax" => {
+                        self.expect_symbol('=')?;
+                        let syntax = self.expect_string()?;
+                        if syntax != "proto3" {
+                            return Err(
+                                self.error(&format!("only proto3 is supported, not {syntax:?}"))
+                            );
+                        }
+                        self.expect_symbol(';')?;
+                    }
+                    "package" => {
+                        self.schema.package = Some(self.expect_full_ident()?);
+                        self.expect_symbol(';')?;
+                    }
+                    "import" | "option" => self.skip_statement()?,
+                    "message" => {
+                        let scope = self.schema.package.clone().unwrap_or_default();
+                        self.parse_message(&scope)?;
+                    }
+                    "enum" => {
+                        let scope = self.schema.package.clone().unwrap_or_default();
+                        self.parse_enum(&scope)?;
+                    }
+                    "service" | "extend" => {
+                        self.expect_full_ident()?;
+                        self.skip_block()?;
+                    }
+                    _ => return Err(self.error(&format!("unexpected '{keyword}'"))),
+                },
+                Token::Symbol(';') => {}
+                token => return Err(self.error(&format!("unexpected {token}"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a message body, after the `message` keyword, and adds it (along with anything
+    /// nested in it) to the schema.
+    fn parse_message(&mut self, scope: &str) -> Result<(), ParseError> {
+        let name = self.expect_ident()?;
+        let full_name = qualify(scope, &name);
+        self.expect_symbol('{')?;
+        self.insert_message(&full_name, false)?;
+
+        loop {
+            match self.next() {
+                Some(Token::Symbol('}')) => break,
+                Some(Token::Symbol(';')) => {}
+                Some(Token::Ident(keyword)) => match keyword.as_str() {
+                    "message" => self.parse_message(&full_name)?,
+                    "enum" => self.parse_enum(&full_name)?,
+                    "option" | "reserved" | "extensions" => self.skip_statement()?,
+                    "extend" => {
+                        self.expect_full_ident()?;
+                        self.skip_block()?;
+                    }
+                    "oneof" => {
+                        // A oneof's fields are ordinary fields as far as the wire format is
+                        // concerned, so we flatten them into the message
+                        self.expect_ident()?;
+                        self.expect_symbol('{')?;
+                        loop {
+                            match self.next() {
+                                Some(Token::Symbol('}')) => break,
+                                Some(Token::Symbol(';')) => {}
+                                Some(Token::Ident(keyword)) if keyword == "option" => {
+                                    self.skip_statement()?
+                                }
+                                Some(Token::Ident(_) | Token::Symbol('.')) => {
+                                    self.position -= 1;
+                                    let type_name = self.expect_type_name()?;
+                                    self.parse_field(&full_name, type_name, false)?
+                                }
+                                _ => return Err(self.error("expected a field in oneof")),
+                            }
+                        }
+                    }
+                    "map" => self.parse_map_field(&full_name)?,
+                    "repeated" => {
+                        let type_name = self.expect_type_name()?;
+                        self.parse_field(&full_name, type_name, true)?;
+                    }
+                    "optional" => {
+                        let type_name = self.expect_type_name()?;
+                        self.parse_field(&full_name, type_name, false)?;
+                    }
+                    "required" => return Err(self.error("'required' is not allowed in proto3")),
+                    _ => {
+                        // No label; the keyword was actually the field's type
+                        self.position -= 1;
+                        let type_name = self.expect_type_name()?;
+                        self.parse_field(&full_name, type_name, false)?;
+                    }
+                },
+                Some(Token::Symbol('.')) => {
+                    // A fully-qualified type name, like `.pkg.Msg field = 1;`
+                    self.position -= 1;
+                    let type_name = self.expect_type_name()?;
+                    self.parse_field(&full_name, type_name, false)?;
+                }
+                Some(token) => return Err(self.error(&format!("unexpected {token}"))),
+                None => return Err(self.error(&format!("message {name} is never closed"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses everything after a field's label and type: `name = number [options];`
+    fn parse_field(
+        &mut self,
+        message_name: &str,
+        type_name: String,
+        repeated: bool,
+    ) -> Result<(), ParseError> {
+        let name = self.expect_ident()?;
+        self.expect_symbol('=')?;
+        let line = self.line();
+        let number = self.expect_int()?;
+        if !(1..=varint::wire::MAX_FIELD_NUMBER as i64).contains(&number) {
+            return Err(self.error(&format!("field number {number} is out of range")));
+        }
+        if self.peek() == Some(&Token::Symbol('[')) {
+            self.skip_brackets()?;
+        }
+        self.expect_symbol(';')?;
+
+        let field_type = FieldType::from_scalar_name(&type_name);
+        let message = self.schema.messages.get_mut(message_name).unwrap();
+        if message.field_by_number(number as u32).is_some() {
+            return Err(error(line, &format!("field number {number} is used twice")));
+        }
+        if field_type.is_none() {
+            self.unresolved_fields.push((
+                message_name.to_string(),
+                message.fields.len(),
+                type_name,
+                message_name.to_string(),
+                line,
+            ));
+        }
+        message.fields.push(Field {
+            name,
+            number: number as u32,
+            // A placeholder until `resolve_types` figures out what the name refers to
+            field_type: field_type.unwrap_or(FieldType::Bytes),
+            repeated,
+        });
+        Ok(())
+    }
+
+    /// Parses `map<K, V> name = number;`, which is shorthand for a repeated field of a nested
+    /// `<Name>Entry` message with a `key` field 1 and a `value` field 2.
+    fn parse_map_field(&mut self, message_name: &str) -> Result<(), ParseError> {
+        self.expect_symbol('<')?;
+        let key_type = self.expect_type_name()?;
+        self.expect_symbol(',')?;
+        let value_type = self.expect_type_name()?;
+        self.expect_symbol('>')?;
+
+        let Some(Token::Ident(name)) = self.peek().cloned() else {
+            return Err(self.error("expected a map field name"));
+        };
+        let entry_name = qualify(message_name, &format!("{}Entry", to_pascal_case(&name)));
+        self.insert_message(&entry_name, true)?;
+        self.parse_field_with_input(&entry_name, key_type, "key = 1;")?;
+        self.parse_field_with_input(&entry_name, value_type, "value = 2;")?;
+
+        self.parse_field(message_name, format!(".{entry_name}"), true)
+    }
+
+    /// Parses a field from the given text rather than from our tokens. Used for the fields of
+    /// synthetic map entry messages.
+    fn parse_field_with_input(
+        &mut self,
+        message_name: &str,
+        type_name: String,
+        input: &str,
+    ) -> Result<(), ParseError> {
+        let line = self.line();
+        let tokens: Vec<(Token, usize)> = tokenize(input)?
+            .into_iter()
+            .map(|(token, _)| (token, line))
+            .collect();
+        let num_tokens = tokens.len();
+        self.tokens.splice(self.position..self.position, tokens);
+        let end = self.position + num_tokens;
+        self.parse_field(message_name, type_name, false)?;
+        debug_assert_eq!(self.position, end);
+        // The map's type name resolves relative to the message containing the map field, not
+        // the entry message, so fix up the scope we just recorded.
+        if let Some(unresolved) = self.unresolved_fields.last_mut() {
+            if unresolved.0 == message_name {
+                unresolved.3 = message_name
+                    .rsplit_once('.')
+                    .map_or("", |(scope, _)| scope)
+                    .to_string();
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_enum(&mut self, scope: &str) -> Result<(), ParseError> {
+        let name = self.expect_ident()?;
+        let full_name = qualify(scope, &name);
+        self.expect_symbol('{')?;
+        self.check_unique_name(&full_name)?;
+
+        let mut values = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Symbol('}')) => break,
+                Some(Token::Symbol(';')) => {}
+                Some(Token::Ident(keyword)) if keyword == "option" || keyword == "reserved" => {
+                    self.skip_statement()?
+                }
+                Some(Token::Ident(value_name)) => {
+                    self.expect_symbol('=')?;
+                    let negative = self.peek() == Some(&Token::Symbol('-'));
+                    if negative {
+                        self.next();
+                    }
+                    let number = self.expect_int()?;
+                    let number = if negative { -number } else { number };
+                    let number = i32::try_from(number)
+                        .map_err(|_| self.error(&format!("enum value {number} is out of range")))?;
+                    if self.peek() == Some(&Token::Symbol('[')) {
+                        self.skip_brackets()?;
+                    }
+                    self.expect_symbol(';')?;
+                    values.push((value_name, number));
+                }
+                Some(token) => return Err(self.error(&format!("unexpected {token}"))),
+                None => return Err(self.error(&format!("enum {name} is never closed"))),
+            }
+        }
+
+        if values.first().is_none_or(|(_, number)| *number != 0) {
+            return Err(self.error(&format!(
+                "the first value of enum {name} must be zero in proto3"
+            )));
+        }
+        self.schema
+            .enums
+            .insert(full_name.clone(), EnumType { full_name, values });
+        Ok(())
+    }
+
+    fn insert_message(&mut self, full_name: &str, map_entry: bool) -> Result<(), ParseError> {
+        self.check_unique_name(full_name)?;
+        self.schema.messages.insert(
+            full_name.to_string(),
+            MessageType {
+                full_name: full_name.to_string(),
+                fields: Vec::new(),
+                map_entry,
+            },
+        );
+        Ok(())
+    }
+
+    fn check_unique_name(&self, full_name: &str) -> Result<(), ParseError> {
+        if self.schema.messages.contains_key(full_name) || self.schema.enums.contains_key(full_name)
+        {
+            return Err(self.error(&format!("{full_name} is defined twice")));
+        }
+        Ok(())
+    }
+
+    /// Resolve every message or enum type name used by a field, now that we know every type
+    /// that's defined. Like protoc, we search for a relative name in the field's own message
+    /// first, then in each enclosing scope in turn, all the way out to the top level.
+    fn resolve_types(&mut self) -> Result<(), ParseError> {
+        for (message_name, field_i, type_name, scope, line) in
+            std::mem::take(&mut self.unresolved_fields)
+        {
+            let field_type = self
+                .lookup_type(&type_name, &scope)
+                .ok_or_else(|| error(line, &format!("unknown type '{type_name}'")))?;
+            self.schema.messages.get_mut(&message_name).unwrap().fields[field_i].field_type =
+                field_type;
+        }
+        Ok(())
+    }
+
+    fn lookup_type(&self, type_name: &str, scope: &str) -> Option<FieldType> {
+        let find = |full_name: &str| {
+            if self.schema.messages.contains_key(full_name) {
+                Some(FieldType::Message(full_name.to_string()))
+            } else if self.schema.enums.contains_key(full_name) {
+                Some(FieldType::Enum(full_name.to_string()))
+            } else {
+                None
+            }
+        };
+
+        if let Some(absolute) = type_name.strip_prefix('.') {
+            return find(absolute);
+        }
+        let mut scope = scope;
+        loop {
+            if let Some(field_type) = find(&qualify(scope, type_name)) {
+                return Some(field_type);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map_or("", |(outer, _)| outer);
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position.saturating_sub(1))
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        error(self.line(), message)
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), ParseError> {
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            Some(token) => Err(self.error(&format!("expected '{symbol}' but found {token}"))),
+            None => Err(self.error(&format!("expected '{symbol}' but the file ended"))),
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            Some(token) => Err(self.error(&format!("expected a name but found {token}"))),
+            None => Err(self.error("expected a name but the file ended")),
+        }
+    }
+
+    /// A dotted name like `foo.bar.Baz`.
+    fn expect_full_ident(&mut self) -> Result<String, ParseError> {
+        let mut ident = self.expect_ident()?;
+        while self.peek() == Some(&Token::Symbol('.')) {
+            self.next();
+            ident.push('.');
+            ident.push_str(&self.expect_ident()?);
+        }
+        Ok(ident)
+    }
+
+    /// A type name, which may be fully-qualified with a leading dot, like `.foo.Bar`.
+    fn expect_type_name(&mut self) -> Result<String, ParseError> {
+        if self.peek() == Some(&Token::Symbol('.')) {
+            self.next();
+            return Ok(format!(".{}", self.expect_full_ident()?));
+        }
+        self.expect_full_ident()
+    }
+
+    fn expect_int(&mut self) -> Result<i64, ParseError> {
+        match self.next() {
+            Some(Token::Int(n)) => Ok(n),
+            Some(token) => Err(self.error(&format!("expected an integer but found {token}"))),
+            None => Err(self.error("expected an integer but the file ended")),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(s),
+            Some(token) => Err(self.error(&format!("expected a string but found {token}"))),
+            None => Err(self.error("expected a string but the file ended")),
+        }
+    }
+
+    /// Skip ahead past the next semicolon, for statements we don't care about. Option values
+    /// can be braced message literals, so we skip those as a whole in case they contain
+    /// semicolons of their own.
+    fn skip_statement(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                Some(Token::Symbol(';')) => {
+                    self.next();
+                    return Ok(());
+                }
+                Some(Token::Symbol('{')) => {
+                    self.skip_block()?;
+                }
+                Some(_) => {
+                    self.next();
+                }
+                None => return Err(self.error("expected ';' but the file ended")),
+            }
+        }
+    }
+
+    /// Skip a `{ ... }` block, including any blocks nested inside it.
+    fn skip_block(&mut self) -> Result<(), ParseError> {
+        self.skip_balanced('{', '}')
+    }
+
+    /// Skip a `[ ... ]` list of field or enum value options.
+    fn skip_brackets(&mut self) -> Result<(), ParseError> {
+        self.skip_balanced('[', ']')
+    }
+
+    fn skip_balanced(&mut self, open: char, close: char) -> Result<(), ParseError> {
+        self.expect_symbol(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Symbol(c)) if c == open => depth += 1,
+                Some(Token::Symbol(c)) if c == close => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error(&format!("expected '{close}' but the file ended"))),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// `my_map` => `MyMap`, the way protoc names map entry messages.
+fn to_pascal_case(name: &str) -> String {
+    let mut pascal = String::new();
+    let mut capitalize_next = true;
+    for c in name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            pascal.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            pascal.push(c);
+        }
+    }
+    pascal
+}
@@ -0,0 +1,92 @@
+use super::{DecodedMessage, Schema, UnknownValue, Value};
+
+use std::fmt::Write;
+
+const INDENT: &str = "  ";
+
+/// Render a decoded message in protobuf's text format, the same way `protoc --decode` does:
+/// one `field_name: value` line per field, with nested messages indented inside braces.
+/// Fields the schema doesn't know about are shown by number, as `protoc --decode_raw` would.
+pub fn render(schema: &Schema, message: &DecodedMessage) -> String {
+    let mut out = String::new();
+    render_message(schema, message, 0, &mut out);
+    out
+}
+
+fn render_message(schema: &Schema, message: &DecodedMessage, depth: usize, out: &mut String) {
+    let indent = INDENT.repeat(depth);
+    let message_type = schema.message(&message.type_name);
+
+    for field in &message.fields {
+        let declared = message_type.and_then(|m| m.field_by_number(field.number));
+        let name = match declared {
+            Some(declared) => declared.name.clone(),
+            None => field.number.to_string(),
+        };
+
+        let rendered = match &field.value {
+            Value::Int32(n) => n.to_string(),
+            Value::Int64(n) => n.to_string(),
+            Value::Uint32(n) => n.to_string(),
+            Value::Uint64(n) => n.to_string(),
+            Value::Float(n) => format_float(*n as f64, n.to_string()),
+            Value::Double(n) => format_float(*n, n.to_string()),
+            Value::Bool(b) => b.to_string(),
+            Value::String(s) => format!("\"{}\"", escape_bytes(s.as_bytes())),
+            Value::Bytes(bytes) => format!("\"{}\"", escape_bytes(bytes)),
+            Value::Enum(n) => {
+                let enum_type = declared.and_then(|declared| match &declared.field_type {
+                    super::FieldType::Enum(enum_name) => schema.enum_type(enum_name),
+                    _ => None,
+                });
+                match enum_type.and_then(|enum_type| enum_type.name_of(*n)) {
+                    Some(value_name) => value_name.to_string(),
+                    None => n.to_string(),
+                }
+            }
+            Value::Message(nested) => {
+                writeln!(out, "{indent}{name} {{").unwrap();
+                render_message(schema, nested, depth + 1, out);
+                writeln!(out, "{indent}}}").unwrap();
+                continue;
+            }
+            Value::Unknown(UnknownValue::Varint(n)) => n.to_string(),
+            Value::Unknown(UnknownValue::I64(n)) => format!("0x{n:016x}"),
+            Value::Unknown(UnknownValue::I32(n)) => format!("0x{n:08x}"),
+            Value::Unknown(UnknownValue::Len(bytes)) => format!("\"{}\"", escape_bytes(bytes)),
+        };
+        writeln!(out, "{indent}{name}: {rendered}").unwrap();
+    }
+}
+
+// Text format spells the special values differently from Rust's Display
+fn format_float(n: f64, displayed: String) -> String {
+    if n.is_nan() {
+        "nan".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        displayed
+    }
+}
+
+/// Escape a string or bytes the way protoc does (C's escapes), so that text format can parse
+/// them back: printable ASCII stays as-is, the usual control characters and quotes get a
+/// backslash, and every other byte becomes a three-digit octal escape like `\377`. That
+/// includes each byte of a non-ASCII character in a string, just as protoc prints them.
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &byte in bytes {
+        match byte {
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'"' => escaped.push_str("\\\""),
+            b'\'' => escaped.push_str("\\'"),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(byte as char),
+            _ => write!(escaped, "\\{byte:03o}").unwrap(),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,253 @@
+use proto_schema::{decode, parse, render, MessageDecodeError, Schema, UnknownValue, Value};
+use varint::wire::WireType;
+use varint::writer::MessageWriter;
+
+const SCHEMA: &str = r#"
+syntax = "proto3";
+package test;
+
+message Scalars {
+  int32 int32_field = 1;
+  int64 int64_field = 2;
+  uint32 uint32_field = 3;
+  uint64 uint64_field = 4;
+  sint32 sint32_field = 5;
+  sint64 sint64_field = 6;
+  fixed32 fixed32_field = 7;
+  fixed64 fixed64_field = 8;
+  sfixed32 sfixed32_field = 9;
+  sfixed64 sfixed64_field = 10;
+  float float_field = 11;
+  double double_field = 12;
+  bool bool_field = 13;
+  string string_field = 14;
+  bytes bytes_field = 15;
+}
+
+enum Color {
+  COLOR_UNSPECIFIED = 0;
+  RED = 1;
+}
+
+message Outer {
+  message Inner {
+    Color color = 1;
+  }
+  Inner inner = 1;
+  repeated sint32 numbers = 2;
+  repeated fixed32 fixed_numbers = 3;
+  repeated string names = 4;
+  map<string, int32> counts = 5;
+}
+"#;
+
+fn schema() -> Schema {
+    parse(SCHEMA).unwrap()
+}
+
+fn values(schema: &Schema, type_name: &str, bytes: &[u8]) -> Vec<(u32, Value)> {
+    decode(schema, type_name, bytes)
+        .unwrap()
+        .fields
+        .into_iter()
+        .map(|field| (field.number, field.value))
+        .collect()
+}
+
+#[test]
+fn test_scalar_types() {
+    let bytes = MessageWriter::new()
+        .varint(1, -2i64 as u64)
+        .varint(2, -2i64 as u64)
+        .varint(3, 150)
+        .varint(4, u64::MAX)
+        .sint(5, -2)
+        .sint(6, -2)
+        .fixed32(7, 7)
+        .fixed64(8, 8)
+        .fixed32(9, -9i32 as u32)
+        .fixed64(10, -10i64 as u64)
+        .fixed32(11, 1.5f32.to_bits())
+        .fixed64(12, (-0.25f64).to_bits())
+        .varint(13, 1)
+        .string(14, "hi")
+        .bytes(15, &[0xff])
+        .finish();
+
+    assert_eq!(
+        values(&schema(), "test.Scalars", &bytes),
+        vec![
+            (1, Value::Int32(-2)),
+            (2, Value::Int64(-2)),
+            (3, Value::Uint32(150)),
+            (4, Value::Uint64(u64::MAX)),
+            (5, Value::Int32(-2)),
+            (6, Value::Int64(-2)),
+            (7, Value::Uint32(7)),
+            (8, Value::Uint64(8)),
+            (9, Value::Int32(-9)),
+            (10, Value::Int64(-10)),
+            (11, Value::Float(1.5)),
+            (12, Value::Double(-0.25)),
+            (13, Value::Bool(true)),
+            (14, Value::String("hi".to_string())),
+            (15, Value::Bytes(vec![0xff])),
+        ]
+    );
+}
+
+#[test]
+fn test_int32_truncation() {
+    let bytes = MessageWriter::new().varint(1, 0x1_0000_0005).finish();
+    assert_eq!(
+        values(&schema(), "test.Scalars", &bytes),
+        vec![(1, Value::Int32(5))]
+    );
+}
+
+#[test]
+fn test_packed_and_unpacked() {
+    let schema = schema();
+    let zigzagged: Vec<u64> = [-1i64, 1, -2]
+        .iter()
+        .map(|&n| varint::zigzag::encode_i64(n))
+        .collect();
+    let mut fixed = Vec::new();
+    fixed.extend_from_slice(&1u32.to_le_bytes());
+    fixed.extend_from_slice(&2u32.to_le_bytes());
+
+    let bytes = MessageWriter::new()
+        .packed(2, &zigzagged)
+        .sint(2, 3)
+        .bytes(3, &fixed)
+        .finish();
+    assert_eq!(
+        values(&schema, "test.Outer", &bytes),
+        vec![
+            (2, Value::Int32(-1)),
+            (2, Value::Int32(1)),
+            (2, Value::Int32(-2)),
+            (2, Value::Int32(3)),
+            (3, Value::Uint32(1)),
+            (3, Value::Uint32(2)),
+        ]
+    );
+
+    let truncated = MessageWriter::new().bytes(3, &[1, 2, 3]).finish();
+    assert_eq!(
+        decode(&schema, "test.Outer", &truncated),
+        Err(MessageDecodeError::InvalidPacked {
+            field: "fixed_numbers".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_errors() {
+    let schema = schema();
+    assert_eq!(
+        decode(&schema, "test.Missing", &[]),
+        Err(MessageDecodeError::UnknownMessageType(
+            "test.Missing".to_string()
+        ))
+    );
+
+    let mismatched = MessageWriter::new().fixed32(14, 1).finish();
+    assert_eq!(
+        decode(&schema, "test.Scalars", &mismatched),
+        Err(MessageDecodeError::WireTypeMismatch {
+            field: "string_field".to_string(),
+            expected: WireType::Len,
+            found: WireType::I32,
+        })
+    );
+
+    let invalid_utf8 = MessageWriter::new().bytes(14, &[0xff]).finish();
+    assert_eq!(
+        decode(&schema, "test.Scalars", &invalid_utf8),
+        Err(MessageDecodeError::InvalidUtf8 {
+            field: "string_field".to_string()
+        })
+    );
+
+    assert!(matches!(
+        decode(&schema, "test.Scalars", &[0x08]),
+        Err(MessageDecodeError::Wire(_))
+    ));
+}
+
+#[test]
+fn test_unknown_fields() {
+    let bytes = MessageWriter::new().varint(99, 1).string(100, "x").finish();
+    assert_eq!(
+        values(&schema(), "test.Scalars", &bytes),
+        vec![
+            (99, Value::Unknown(UnknownValue::Varint(1))),
+            (100, Value::Unknown(UnknownValue::Len(b"x".to_vec()))),
+        ]
+    );
+}
+
+#[test]
+fn test_render() {
+    let schema = schema();
+    let bytes = MessageWriter::new()
+        .message(1, |inner| inner.varint(1, 1))
+        .message(1, |inner| inner.varint(1, 7))
+        .sint(2, -5)
+        .string(4, "a \"quoted\" name")
+        .message(5, |entry| entry.string(1, "apples").varint(2, 3))
+        .varint(6, 42)
+        .finish();
+
+    let message = decode(&schema, "test.Outer", &bytes).unwrap();
+    assert_eq!(
+        render(&schema, &message),
+        r#"inner {
+  color: RED
+}
+inner {
+  color: 7
+}
+numbers: -5
+names: "a \"quoted\" name"
+counts {
+  key: "apples"
+  value: 3
+}
+6: 42
+"#
+    );
+}
+
+#[test]
+fn test_render_scalars() {
+    let schema = schema();
+    let bytes = MessageWriter::new()
+        .fixed32(11, f32::NAN.to_bits())
+        .fixed64(12, 2.0f64.to_bits())
+        .varint(13, 0)
+        .bytes(15, b"\x00A")
+        .finish();
+    let message = decode(&schema, "test.Scalars", &bytes).unwrap();
+    assert_eq!(
+        render(&schema, &message),
+        "float_field: nan\ndouble_field: 2\nbool_field: false\nbytes_field: \"\\000A\"\n"
+    );
+}
+
+#[test]
+fn test_render_string_escapes() {
+    // The escapes text format understands, as protoc writes them: no `\u{1b}` or `\xNN`, and
+    // every byte of a non-ASCII character in octal
+    let schema = schema();
+    let bytes = MessageWriter::new()
+        .string(14, "it's\t\"é\"\x1b\\")
+        .finish();
+    let message = decode(&schema, "test.Scalars", &bytes).unwrap();
+    assert_eq!(
+        render(&schema, &message),
+        r#"string_field: "it\'s\t\"\303\251\"\033\\"
+"#
+    );
+}
@@ -0,0 +1,277 @@
+use proto_schema::{decode, from_json, parse, to_json, JsonError, Schema};
+use serde_json::json;
+use varint::writer::MessageWriter;
+
+const SCHEMA: &str = r#"
+syntax = "proto3";
+package test;
+
+enum Status {
+  STATUS_UNSPECIFIED = 0;
+  ACTIVE = 1;
+  BANNED = 2;
+}
+
+message Scalars {
+  int32 int32_field = 1;
+  int64 int64_field = 2;
+  uint32 uint32_field = 3;
+  uint64 uint64_field = 4;
+  sint32 sint32_field = 5;
+  sint64 sint64_field = 6;
+  fixed32 fixed32_field = 7;
+  fixed64 fixed64_field = 8;
+  sfixed32 sfixed32_field = 9;
+  sfixed64 sfixed64_field = 10;
+  float float_field = 11;
+  double double_field = 12;
+  bool bool_field = 13;
+  string string_field = 14;
+  bytes bytes_field = 15;
+  Status status = 16;
+}
+
+message Account {
+  message Address {
+    string street_name = 1;
+  }
+  string user_name = 1;
+  repeated sint64 balances = 2;
+  repeated double ratios = 3;
+  repeated Address addresses = 4;
+  map<string, Status> statuses = 5;
+  map<int32, Address> by_id = 6;
+  Scalars scalars = 7;
+}
+"#;
+
+fn schema() -> Schema {
+    parse(SCHEMA).unwrap()
+}
+
+fn binary_to_json(schema: &Schema, type_name: &str, bytes: &[u8]) -> serde_json::Value {
+    to_json(schema, &decode(schema, type_name, bytes).unwrap())
+}
+
+/// Binary -> JSON -> binary should give back exactly the same bytes, as long as the original
+/// was encoded the canonical way (fields in number order, repeated scalars packed).
+fn assert_round_trip(schema: &Schema, type_name: &str, bytes: &[u8]) -> serde_json::Value {
+    let json = binary_to_json(schema, type_name, bytes);
+    assert_eq!(
+        from_json(schema, type_name, &json).unwrap(),
+        bytes,
+        "{json}"
+    );
+    json
+}
+
+#[test]
+fn test_scalars() {
+    let bytes = MessageWriter::new()
+        .varint(1, -2i64 as u64)
+        .varint(2, -3i64 as u64)
+        .varint(3, u32::MAX as u64)
+        .varint(4, u64::MAX)
+        .sint(5, -4)
+        .sint(6, i64::MIN)
+        .fixed32(7, 7)
+        .fixed64(8, 8)
+        .fixed32(9, -9i32 as u32)
+        .fixed64(10, -10i64 as u64)
+        .fixed32(11, 1.1f32.to_bits())
+        .fixed64(12, 0.1f64.to_bits())
+        .varint(13, 1)
+        .string(14, "héllo \"world\"")
+        .bytes(15, &[0x00, 0x01, 0xfe, 0xff])
+        .varint(16, 2)
+        .finish();
+
+    assert_eq!(
+        assert_round_trip(&schema(), "test.Scalars", &bytes),
+        json!({
+            "int32Field": -2,
+            "int64Field": "-3",
+            "uint32Field": 4294967295u32,
+            "uint64Field": "18446744073709551615",
+            "sint32Field": -4,
+            "sint64Field": "-9223372036854775808",
+            "fixed32Field": 7,
+            "fixed64Field": "8",
+            "sfixed32Field": -9,
+            "sfixed64Field": "-10",
+            "floatField": 1.1,
+            "doubleField": 0.1,
+            "boolField": true,
+            "stringField": "héllo \"world\"",
+            "bytesField": "AAH+/w==",
+            "status": "BANNED",
+        })
+    );
+}
+
+#[test]
+fn test_special_floats() {
+    let bytes = MessageWriter::new()
+        .fixed32(11, f32::INFINITY.to_bits())
+        .fixed64(12, f64::NEG_INFINITY.to_bits())
+        .finish();
+    assert_eq!(
+        assert_round_trip(&schema(), "test.Scalars", &bytes),
+        json!({"floatField": "Infinity", "doubleField": "-Infinity"})
+    );
+
+    let nan = MessageWriter::new()
+        .fixed64(12, f64::NAN.to_bits())
+        .finish();
+    assert_eq!(
+        assert_round_trip(&schema(), "test.Scalars", &nan),
+        json!({"doubleField": "NaN"})
+    );
+}
+
+#[test]
+fn test_unknown_enum_value() {
+    let bytes = MessageWriter::new().varint(16, 7).finish();
+    assert_eq!(
+        assert_round_trip(&schema(), "test.Scalars", &bytes),
+        json!({"status": 7})
+    );
+}
+
+#[test]
+fn test_nested_repeated_and_maps() {
+    let mut ratios = Vec::new();
+    ratios.extend_from_slice(&0.5f64.to_le_bytes());
+    ratios.extend_from_slice(&(-2.0f64).to_le_bytes());
+
+    let bytes = MessageWriter::new()
+        .string(1, "ada")
+        .packed(
+            2,
+            &[
+                varint::zigzag::encode_i64(-1),
+                varint::zigzag::encode_i64(5),
+            ],
+        )
+        .bytes(3, &ratios)
+        .message(4, |address| address.string(1, "Main St"))
+        .message(4, |address| address)
+        .message(5, |entry| entry.string(1, "prod").varint(2, 1))
+        .message(5, |entry| entry.string(1, "dev").varint(2, 2))
+        .message(6, |entry| {
+            entry
+                .varint(1, -1i64 as u64)
+                .message(2, |address| address.string(1, "Elm St"))
+        })
+        .message(7, |scalars| scalars.varint(13, 1))
+        .finish();
+
+    assert_eq!(
+        assert_round_trip(&schema(), "test.Account", &bytes),
+        json!({
+            "userName": "ada",
+            "balances": ["-1", "5"],
+            "ratios": [0.5, -2.0],
+            "addresses": [{"streetName": "Main St"}, {}],
+            "statuses": {"prod": "ACTIVE", "dev": "BANNED"},
+            "byId": {"-1": {"streetName": "Elm St"}},
+            "scalars": {"boolField": true},
+        })
+    );
+}
+
+#[test]
+fn test_map_entry_defaults() {
+    // Map entries with their key or value left out still need both in JSON
+    let bytes = MessageWriter::new()
+        .message(5, |entry| entry.string(1, "missing value"))
+        .message(6, |entry| entry)
+        .finish();
+    assert_eq!(
+        binary_to_json(&schema(), "test.Account", &bytes),
+        json!({
+            "statuses": {"missing value": "STATUS_UNSPECIFIED"},
+            "byId": {"0": {}},
+        })
+    );
+}
+
+#[test]
+fn test_lenient_parsing() {
+    let schema = schema();
+    // Original field names, numbers as strings, exponents, URL-safe unpadded base64, enums
+    // by number, and nulls are all accepted
+    let json = json!({
+        "int32_field": "-2",
+        "int64Field": -3,
+        "uint32Field": 1e3,
+        "floatField": "1.5",
+        "bytesField": "AAH-_w",
+        "status": 1,
+        "boolField": null,
+    });
+    let expected = MessageWriter::new()
+        .varint(1, -2i64 as u64)
+        .varint(2, -3i64 as u64)
+        .varint(3, 1000)
+        .fixed32(11, 1.5f32.to_bits())
+        .bytes(15, &[0x00, 0x01, 0xfe, 0xff])
+        .varint(16, 1)
+        .finish();
+    assert_eq!(from_json(&schema, "test.Scalars", &json).unwrap(), expected);
+}
+
+#[test]
+fn test_from_json_errors() {
+    let schema = schema();
+    assert_eq!(
+        from_json(&schema, "test.Missing", &json!({})),
+        Err(JsonError::UnknownMessageType("test.Missing".to_string()))
+    );
+    assert_eq!(
+        from_json(&schema, "test.Scalars", &json!({"nope": 1})),
+        Err(JsonError::UnknownField {
+            type_name: "test.Scalars".to_string(),
+            key: "nope".to_string()
+        })
+    );
+    assert_eq!(
+        from_json(
+            &schema,
+            "test.Scalars",
+            &json!({"int32Field": 1, "int32_field": 2})
+        ),
+        Err(JsonError::DuplicateField {
+            type_name: "test.Scalars".to_string(),
+            field: "int32_field".to_string()
+        })
+    );
+    assert!(matches!(
+        from_json(
+            &schema,
+            "test.Scalars",
+            &json!({"int32Field": null, "int32_field": 2})
+        ),
+        Err(JsonError::DuplicateField { .. })
+    ));
+    assert_eq!(
+        from_json(&schema, "test.Scalars", &json!([])),
+        Err(JsonError::InvalidValue {
+            field: "test.Scalars".to_string(),
+            expected: "an object"
+        })
+    );
+
+    let invalid = |json| match from_json(&schema, "test.Scalars", &json) {
+        Err(JsonError::InvalidValue { field, .. }) => field,
+        result => panic!("expected an invalid value error, got {result:?}"),
+    };
+    assert_eq!(invalid(json!({"int32Field": 2147483648u64})), "int32_field");
+    assert_eq!(invalid(json!({"uint64Field": -1})), "uint64_field");
+    assert_eq!(invalid(json!({"int32Field": 1.5})), "int32_field");
+    assert_eq!(invalid(json!({"floatField": 1e300})), "float_field");
+    assert_eq!(invalid(json!({"boolField": "yes"})), "bool_field");
+    assert_eq!(invalid(json!({"stringField": 1})), "string_field");
+    assert_eq!(invalid(json!({"bytesField": "not base64!"})), "bytes_field");
+    assert_eq!(invalid(json!({"status": "MISSING"})), "status");
+}
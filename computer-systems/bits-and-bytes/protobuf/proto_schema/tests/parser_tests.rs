@@ -0,0 +1,205 @@
+use proto_schema::{parse, FieldType, ParseError};
+
+const SCHEMA: &str = r#"
+syntax = "proto3";
+
+package example.people;
+
+import "google/protobuf/any.proto";
+option java_package = "com.example.people";
+
+// A person, with a block comment thrown in for good measure
+message Person {
+  /* their full name */
+  string name = 1;
+  int32 id = 2 [deprecated = true];
+  repeated string emails = 3;
+  sint64 balance = 4;
+  fixed32 checksum = 5;
+  optional bytes avatar = 6;
+
+  enum PhoneType {
+    option allow_alias = true;
+    PHONE_TYPE_UNSPECIFIED = 0;
+    MOBILE = 1;
+    HOME = 2;
+    CELL = 1;
+  }
+
+  message PhoneNumber {
+    string number = 1;
+    PhoneType type = 2;
+  }
+
+  repeated PhoneNumber phones = 7;
+  map<string, Status> statuses = 8;
+
+  oneof contact {
+    string twitter = 9;
+    .example.people.Person referrer = 10;
+  }
+
+  reserved 11, 15 to 20;
+  reserved "old_name";
+}
+
+enum Status {
+  STATUS_UNSPECIFIED = 0;
+  ACTIVE = 1;
+  BANNED = -1;
+}
+
+service People {
+  rpc Get(Person) returns (Person) { option idempotency_level = NO_SIDE_EFFECTS; }
+}
+"#;
+
+#[test]
+fn test_messages_and_fields() {
+    let schema = parse(SCHEMA).unwrap();
+    assert_eq!(schema.package.as_deref(), Some("example.people"));
+
+    let person = schema.message("example.people.Person").unwrap();
+    let types: Vec<_> = person
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.name.as_str(),
+                field.number,
+                &field.field_type,
+                field.repeated,
+            )
+        })
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("name", 1, &FieldType::String, false),
+            ("id", 2, &FieldType::Int32, false),
+            ("emails", 3, &FieldType::String, true),
+            ("balance", 4, &FieldType::Sint64, false),
+            ("checksum", 5, &FieldType::Fixed32, false),
+            ("avatar", 6, &FieldType::Bytes, false),
+            (
+                "phones",
+                7,
+                &FieldType::Message("example.people.Person.PhoneNumber".to_string()),
+                true
+            ),
+            (
+                "statuses",
+                8,
+                &FieldType::Message("example.people.Person.StatusesEntry".to_string()),
+                true
+            ),
+            ("twitter", 9, &FieldType::String, false),
+            (
+                "referrer",
+                10,
+                &FieldType::Message("example.people.Person".to_string()),
+                false
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_leading_dot_lookup() {
+    let schema = parse(SCHEMA).unwrap();
+    assert!(schema.message(".example.people.Person").is_some());
+    assert!(schema.message("Person").is_none());
+}
+
+#[test]
+fn test_nested_type_resolution() {
+    let schema = parse(SCHEMA).unwrap();
+    let phone = schema.message("example.people.Person.PhoneNumber").unwrap();
+    // `PhoneType` is found in the enclosing message's scope
+    assert_eq!(
+        phone.field_by_name("type").unwrap().field_type,
+        FieldType::Enum("example.people.Person.PhoneType".to_string())
+    );
+}
+
+#[test]
+fn test_map_entry() {
+    let schema = parse(SCHEMA).unwrap();
+    let entry = schema
+        .message("example.people.Person.StatusesEntry")
+        .unwrap();
+    assert!(entry.map_entry);
+    assert_eq!(
+        entry.field_by_number(1).unwrap().field_type,
+        FieldType::String
+    );
+    // `Status` is resolved from where the map was declared, not from inside the entry
+    assert_eq!(
+        entry.field_by_number(2).unwrap().field_type,
+        FieldType::Enum("example.people.Status".to_string())
+    );
+}
+
+#[test]
+fn test_enums() {
+    let schema = parse(SCHEMA).unwrap();
+    let status = schema.enum_type("example.people.Status").unwrap();
+    assert_eq!(status.name_of(-1), Some("BANNED"));
+    assert_eq!(status.number_of("ACTIVE"), Some(1));
+
+    // With aliases, the first name wins
+    let phone_type = schema.enum_type("example.people.Person.PhoneType").unwrap();
+    assert_eq!(phone_type.name_of(1), Some("MOBILE"));
+}
+
+#[test]
+fn test_no_package() {
+    let schema = parse("syntax = \"proto3\"; message A { B b = 1; } message B {}").unwrap();
+    assert_eq!(
+        schema.message("A").unwrap().fields[0].field_type,
+        FieldType::Message("B".to_string())
+    );
+}
+
+fn parse_error(source: &str) -> ParseError {
+    parse(source).unwrap_err()
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        parse_error("syntax = \"proto2\";"),
+        ParseError {
+            line: 1,
+            message: "only proto3 is supported, not \"proto2\"".to_string()
+        }
+    );
+    assert_eq!(
+        parse_error("message A {\n  Missing m = 1;\n}"),
+        ParseError {
+            line: 2,
+            message: "unknown type 'Missing'".to_string()
+        }
+    );
+    assert_eq!(
+        parse_error("message A {\n  int32 a = 1;\n  int32 b = 1;\n}").message,
+        "field number 1 is used twice"
+    );
+    assert_eq!(
+        parse_error("message A { int32 a = 0; }").message,
+        "field number 0 is out of range"
+    );
+    assert_eq!(
+        parse_error("enum E { ONE = 1; }").message,
+        "the first value of enum E must be zero in proto3"
+    );
+    assert_eq!(
+        parse_error("message A { int32 a = 1; }\nmessage A {}").message,
+        "A is defined twice"
+    );
+    assert_eq!(
+        parse_error("message A {\n  int32 a = 1;\n").message,
+        "message A is never closed"
+    );
+    assert_eq!(parse_error("message A { required int32 a = 1; }").line, 1);
+}
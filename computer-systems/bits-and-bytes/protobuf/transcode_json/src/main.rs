@@ -0,0 +1,154 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+
+const USAGE: &str = "Usage: transcode_json to-json|from-json --proto FILE.proto --type pkg.Msg \
+                     [--format hex|base64|raw] [FILE]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    ToJson,
+    FromJson,
+}
+
+// The format of the binary side: the input for to-json, or the output for from-json
+#[derive(Clone, Copy, PartialEq)]
+enum BinaryFormat {
+    Hex,
+    Base64,
+    Raw,
+}
+
+struct Args {
+    command: Command,
+    proto_path: String,
+    type_name: String,
+    format: BinaryFormat,
+    // We read from stdin unless given a file path.
+    path: Option<String>,
+}
+
+fn main() {
+    let args = parse_args();
+
+    let source = fs::read_to_string(&args.proto_path).unwrap_or_else(|e| {
+        fail(&format!("Failed to read {}: {e}", args.proto_path));
+    });
+    let schema = proto_schema::parse(&source).unwrap_or_else(|e| {
+        fail(&format!("Failed to parse {}: {e}", args.proto_path));
+    });
+
+    let mut input = Vec::new();
+    match &args.path {
+        Some(path) => {
+            input = fs::read(path).unwrap_or_else(|e| fail(&format!("Failed to read {path}: {e}")))
+        }
+        None => {
+            io::stdin()
+                .read_to_end(&mut input)
+                .expect("Failed to read from stdin");
+        }
+    }
+
+    match args.command {
+        Command::ToJson => {
+            let bytes = match args.format {
+                BinaryFormat::Raw => input,
+                BinaryFormat::Hex => hex::decode(strip_whitespace(&input))
+                    .expect("please provide valid hexadecimal input"),
+                BinaryFormat::Base64 => BASE64_STANDARD
+                    .decode(strip_whitespace(&input))
+                    .expect("please provide valid base64 input"),
+            };
+            let message = proto_schema::decode(&schema, &args.type_name, &bytes)
+                .unwrap_or_else(|e| fail(&format!("Failed to decode message: {e}")));
+            let json = proto_schema::to_json(&schema, &message);
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+        Command::FromJson => {
+            let json: serde_json::Value = serde_json::from_slice(&input)
+                .unwrap_or_else(|e| fail(&format!("Failed to parse JSON: {e}")));
+            let bytes = proto_schema::from_json(&schema, &args.type_name, &json)
+                .unwrap_or_else(|e| fail(&format!("Failed to encode message: {e}")));
+            match args.format {
+                BinaryFormat::Raw => io::stdout()
+                    .write_all(&bytes)
+                    .expect("Failed to write to stdout"),
+                BinaryFormat::Hex => println!("{}", hex::encode(&bytes)),
+                BinaryFormat::Base64 => println!("{}", BASE64_STANDARD.encode(&bytes)),
+            }
+        }
+    }
+}
+
+fn strip_whitespace(input: &[u8]) -> Vec<u8> {
+    input
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect()
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(1);
+}
+
+fn parse_args() -> Args {
+    let mut raw_args = env::args().skip(1);
+    let command = match raw_args.next().as_deref() {
+        Some("to-json") => Command::ToJson,
+        Some("from-json") => Command::FromJson,
+        Some(other) => usage_error(&format!("Unrecognized command '{other}'")),
+        None => usage_error("A command must be given"),
+    };
+
+    let mut proto_path = None;
+    let mut type_name = None;
+    let mut format = BinaryFormat::Hex;
+    let mut path = None;
+
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--proto" => {
+                proto_path = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--proto must be given a file")),
+                )
+            }
+            "--type" => {
+                type_name = Some(
+                    raw_args
+                        .next()
+                        .unwrap_or_else(|| usage_error("--type must be given a message name")),
+                )
+            }
+            "--format" => {
+                format = match raw_args.next().as_deref() {
+                    Some("hex") => BinaryFormat::Hex,
+                    Some("base64") => BinaryFormat::Base64,
+                    Some("raw") => BinaryFormat::Raw,
+                    _ => usage_error("--format must be one of hex, base64 or raw"),
+                }
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("Unrecognized argument '{arg}'")),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage_error("Only one file may be given"),
+        }
+    }
+
+    Args {
+        command,
+        proto_path: proto_path.unwrap_or_else(|| usage_error("--proto is required")),
+        type_name: type_name.unwrap_or_else(|| usage_error("--type is required")),
+        format,
+        path,
+    }
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{msg}. {USAGE}");
+    std::process::exit(2);
+}
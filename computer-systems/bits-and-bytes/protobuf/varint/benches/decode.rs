@@ -1,5 +1,4 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use varint;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 
 fn bench_decode(c: &mut Criterion) {
     let mut group = c.benchmark_group("decode");
@@ -22,5 +21,62 @@ fn bench_decode(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_decode);
+/// Encodes `count` pseudo-random numbers back-to-back, like a packed repeated field. Each
+/// number is shifted right by a random amount, so every encoded length shows up, but shorter
+/// varints (as in most real-world data) are the majority.
+fn packed_varints(count: usize, max_bits: u32) -> Vec<u8> {
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut bytes = Vec::new();
+    let mut buf = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    for _ in 0..count {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        let n = (state >> 1) >> (63 - max_bits + (state % max_bits as u64) as u32);
+        let num_bytes = varint::encode(n, &mut buf);
+        bytes.extend_from_slice(&buf[..num_bytes]);
+    }
+    bytes
+}
+
+fn bench_decode_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_many");
+
+    let num_varints = 10_000;
+    for max_bits in [14, 32, 63] {
+        let bytes = packed_varints(num_varints, max_bits);
+        group.throughput(Throughput::Elements(num_varints as u64));
+
+        // Baseline: a byte-at-a-time `try_decode` call per varint
+        group.bench_with_input(
+            BenchmarkId::new("try_decode_loop", max_bits),
+            &bytes,
+            |b, bytes| {
+                let mut out = Vec::with_capacity(num_varints);
+                b.iter(|| {
+                    out.clear();
+                    let mut offset = 0;
+                    while offset < bytes.len() {
+                        let (n, num_bytes) = varint::try_decode(&bytes[offset..]).unwrap();
+                        out.push(n);
+                        offset += num_bytes;
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("decode_many", max_bits),
+            &bytes,
+            |b, bytes| {
+                let mut out = Vec::with_capacity(num_varints);
+                b.iter(|| {
+                    out.clear();
+                    varint::decode_many(bytes, &mut out).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_decode_many);
 criterion_main!(benches);
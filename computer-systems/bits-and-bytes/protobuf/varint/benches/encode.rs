@@ -1,5 +1,4 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use varint;
 
 fn bench_encode(c: &mut Criterion) {
     let mut group = c.benchmark_group("encode");
@@ -0,0 +1,88 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use varint::formats::{Leb128, PrefixVarint, SignedLeb128, Sqlite, VarintFormat};
+
+/// `count` pseudo-random numbers, each shifted right by a random amount so that every encoded
+/// length shows up, with shorter ones the majority (same scheme as decode.rs).
+fn numbers(count: usize, max_bits: u32) -> Vec<u64> {
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    (0..count)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 1) >> (63 - max_bits + (state % max_bits as u64) as u32)
+        })
+        .collect()
+}
+
+fn encode_all<F: VarintFormat>(numbers: &[F::Value]) -> Vec<u8> {
+    let mut bytes = vec![0; numbers.len() * F::MAX_ENCODED_LEN];
+    let mut offset = 0;
+    for &n in numbers {
+        offset += F::encode(n, &mut bytes[offset..]);
+    }
+    bytes.truncate(offset);
+    bytes
+}
+
+fn decode_all<F: VarintFormat>(mut bytes: &[u8], out: &mut Vec<F::Value>) {
+    while !bytes.is_empty() {
+        let (n, num_bytes) = F::decode(bytes).unwrap();
+        out.push(n);
+        bytes = &bytes[num_bytes..];
+    }
+}
+
+fn bench_format<F: VarintFormat>(c: &mut Criterion, name: &str, inputs: &[(u32, Vec<F::Value>)]) {
+    for (max_bits, numbers) in inputs {
+        // Density doesn't need timing, so we just report it alongside
+        let encoded = encode_all::<F>(numbers);
+        println!(
+            "{name}/{max_bits}: {:.3} bytes per value",
+            encoded.len() as f64 / numbers.len() as f64
+        );
+
+        let mut group = c.benchmark_group("format_encode");
+        group.throughput(Throughput::Elements(numbers.len() as u64));
+        group.bench_with_input(BenchmarkId::new(name, max_bits), numbers, |b, numbers| {
+            b.iter(|| encode_all::<F>(numbers))
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("format_decode");
+        group.throughput(Throughput::Elements(numbers.len() as u64));
+        group.bench_with_input(BenchmarkId::new(name, max_bits), &encoded, |b, encoded| {
+            let mut out = Vec::with_capacity(numbers.len());
+            b.iter(|| {
+                out.clear();
+                decode_all::<F>(encoded, &mut out);
+            })
+        });
+        group.finish();
+    }
+}
+
+fn bench_formats(c: &mut Criterion) {
+    let num_values = 10_000;
+    let unsigned: Vec<_> = [14, 32, 63]
+        .into_iter()
+        .map(|max_bits| (max_bits, numbers(num_values, max_bits)))
+        .collect();
+    // Half negative, with the same spread of magnitudes
+    let signed: Vec<_> = unsigned
+        .iter()
+        .map(|(max_bits, numbers)| {
+            let numbers = numbers
+                .iter()
+                .map(|&n| if n % 2 == 0 { n as i64 } else { !(n as i64) })
+                .collect();
+            (*max_bits, numbers)
+        })
+        .collect();
+
+    bench_format::<Leb128>(c, "leb128", &unsigned);
+    bench_format::<Sqlite>(c, "sqlite", &unsigned);
+    bench_format::<PrefixVarint>(c, "prefix_varint", &unsigned);
+    bench_format::<SignedLeb128>(c, "signed_leb128", &signed);
+}
+
+criterion_group!(benches, bench_formats);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::panic;
+
+// Feeds arbitrary bytes to every decoder in the crate. None of them should ever panic, with
+// one documented exception: `decode` panics whenever `try_decode` returns an error. That's a
+// truncated varint (the bytes run out while every byte so far has a continuation bit), an
+// overlong one (the first ten bytes all have continuation bits) or an overflowing one (the
+// tenth byte sets bits beyond the 64th). So we check that `decode` agrees with `try_decode`
+// when it succeeds, and panics exactly when it fails.
+fuzz_target!(|bytes: &[u8]| {
+    match varint::try_decode(bytes) {
+        Ok((n, num_bytes)) => {
+            assert!(num_bytes <= varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64);
+            assert!(varint::encoded_len(n) <= num_bytes);
+            assert_eq!(varint::decode(bytes), n);
+        }
+        Err(_) => {
+            // libFuzzer's panic hook aborts the process, treating any panic as a crash, so we
+            // swap it out while we catch the one we expect.
+            let fuzzer_hook = panic::take_hook();
+            panic::set_hook(Box::new(|_| {}));
+            let result = panic::catch_unwind(|| varint::decode(bytes));
+            panic::set_hook(fuzzer_hook);
+            assert!(result.is_err(), "decode accepted bytes that try_decode rejected");
+        }
+    }
+
+    let _ = varint::try_decode_signed(bytes);
+
+    let mut decoded = Vec::new();
+    if let Ok(num_decoded) = varint::decode_many(bytes, &mut decoded) {
+        assert_eq!(num_decoded, decoded.len());
+    }
+
+    let _ = varint::packed::decode(bytes);
+    let _ = varint::wire::parse(bytes);
+});
@@ -0,0 +1,36 @@
+use crate::{LSB_MASK, MSB_MASK};
+
+use core::fmt;
+
+/// Displays encoded varint bytes one per line, each one split into its continuation bit and
+/// its seven payload bits. For example, the two bytes encoding 150 display as:
+/// ```text
+/// 96  1 0010110  (more bytes follow)
+/// 01  0 0000001  (last byte)
+/// ```
+/// Remember that the payload bits are little-endian: the first line holds the least
+/// significant seven bits of the decoded integer.
+pub struct Breakdown<'a>(pub &'a [u8]);
+
+impl fmt::Display for Breakdown<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let has_continuation_bit = byte & MSB_MASK != 0;
+            let description = if has_continuation_bit {
+                "(more bytes follow)"
+            } else {
+                "(last byte)"
+            };
+            write!(
+                f,
+                "{byte:02x}  {} {:07b}  {description}",
+                has_continuation_bit as u8,
+                byte & LSB_MASK
+            )?;
+        }
+        Ok(())
+    }
+}
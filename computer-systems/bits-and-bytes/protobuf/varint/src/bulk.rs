@@ -0,0 +1,65 @@
+use crate::DecodeError;
+
+use alloc::vec::Vec;
+
+// One bit per byte of a u64: each byte's continuation bit, or each byte's seven payload bits.
+const MSB_MASK_WORD: u64 = 0x8080_8080_8080_8080;
+const LSB_MASK_WORD: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+
+/// Decodes every varint in the given slice (for instance, the contents of a packed repeated
+/// field), appending them to `out`. Returns the number of varints appended. On error, `out`
+/// keeps every varint that was decoded before the bad one.
+///
+/// Rather than looping over a byte at a time like `try_decode`, this loads eight bytes at once
+/// into a u64 and finds the varint's last byte with a single mask and `trailing_zeros`. Then
+/// it squeezes the continuation bits out of the whole word in three shift-and-mask steps.
+/// This handles any varint up to eight bytes long (so anything below 2^56). Longer varints,
+/// and the last few bytes of the slice where a full word can't be loaded, use `try_decode`.
+pub fn decode_many(bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, DecodeError> {
+    let initial_len = out.len();
+    let mut offset = 0;
+
+    // Fast path, for as long as a whole word can be loaded
+    while offset + 8 <= bytes.len() {
+        let word = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        // Bytes without a continuation bit become 0x80 here; every other byte becomes 0.
+        let last_bytes = !word & MSB_MASK_WORD;
+        if last_bytes == 0 {
+            // Nine or ten bytes long; leave this one to the slow path
+            let (n, num_bytes) = crate::try_decode(&bytes[offset..])?;
+            out.push(n);
+            offset += num_bytes;
+            continue;
+        }
+
+        // The lowest set bit marks the first varint's last byte (little-endian loads put our
+        // first byte in the least significant position). Everything up to and including that
+        // bit belongs to the varint.
+        let varint_mask = last_bytes ^ (last_bytes - 1);
+        out.push(compact(word & varint_mask));
+        offset += (last_bytes.trailing_zeros() / 8 + 1) as usize;
+    }
+
+    // Slow path for the last few bytes
+    while offset < bytes.len() {
+        let (n, num_bytes) = crate::try_decode(&bytes[offset..])?;
+        out.push(n);
+        offset += num_bytes;
+    }
+
+    Ok(out.len() - initial_len)
+}
+
+/// Given a word holding a single varint (one to eight bytes long, with every byte past its end
+/// already zeroed), returns the varint's value by dropping its continuation bits and packing
+/// its 7-bit groups together.
+fn compact(word: u64) -> u64 {
+    let mut x = word & LSB_MASK_WORD;
+
+    // Each step halves the number of gaps. First, pairs of 7-bit groups close the one-bit gap
+    // between them into 14-bit groups; then pairs of those close a two-bit gap into 28-bit
+    // groups; and finally the two 28-bit groups close a four-bit gap.
+    x = (x & 0x007F_007F_007F_007F) | ((x & 0x7F00_7F00_7F00_7F00) >> 1);
+    x = (x & 0x0000_3FFF_0000_3FFF) | ((x & 0x3FFF_0000_3FFF_0000) >> 2);
+    (x & 0x0000_0000_0FFF_FFFF) | ((x & 0x0FFF_FFFF_0000_0000) >> 4)
+}
@@ -0,0 +1,237 @@
+use crate::{DecodeError, LSB_MASK, MSB_MASK};
+
+/// A variable-length integer encoding. Protobuf's varint is only one of several in common use,
+/// and they trade off density against how cheaply a decoder can find the end of a value. The
+/// types below are markers for each format, so generic code (and our benchmarks) can switch
+/// between them with a type parameter, as in `F::encode(n, &mut buf)`.
+pub trait VarintFormat {
+    /// The integer type this format encodes.
+    type Value: Copy;
+
+    /// The most bytes an encoding of any value can take up.
+    const MAX_ENCODED_LEN: usize;
+
+    /// Adds encoded bytes to the given slice. Returns the number of bytes added.
+    fn encode(n: Self::Value, bytes: &mut [u8]) -> usize;
+
+    /// The number of bytes `encode` will add for the given value.
+    fn encoded_len(n: Self::Value) -> usize;
+
+    /// Decodes the first value in the given bytes slice, returning it along with the number of
+    /// bytes it was encoded in.
+    fn decode(bytes: &[u8]) -> Result<(Self::Value, usize), DecodeError>;
+}
+
+/// Unsigned LEB128, as used by DWARF and WebAssembly. This is exactly protobuf's varint
+/// format: seven bits per byte, least significant group first, with the top bit of every byte
+/// but the last set.
+pub struct Leb128;
+
+impl VarintFormat for Leb128 {
+    type Value = u64;
+    const MAX_ENCODED_LEN: usize = crate::MAX_NUM_ENCODABLE_BYTES_FOR_U64;
+
+    fn encode(n: u64, bytes: &mut [u8]) -> usize {
+        crate::encode(n, bytes)
+    }
+
+    fn encoded_len(n: u64) -> usize {
+        crate::encoded_len(n)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
+        crate::try_decode(bytes)
+    }
+}
+
+/// Signed LEB128, as used by DWARF for things like stack offsets. Rather than ZigZag-encoding
+/// like protobuf's `sint64`, it stores the two's complement bits directly and stops as soon as
+/// every remaining bit is a copy of the sign bit. The decoder knows the sign from bit 6 of the
+/// last byte, and sign-extends from there.
+pub struct SignedLeb128;
+
+// Bit 6 of the last byte, which is the sign bit of the whole value
+const SIGN_BIT: u8 = 0b0100_0000;
+
+impl VarintFormat for SignedLeb128 {
+    type Value = i64;
+    const MAX_ENCODED_LEN: usize = 10;
+
+    fn encode(n: i64, bytes: &mut [u8]) -> usize {
+        let mut num_added_bytes = 0;
+        let mut remaining_bits = n;
+
+        loop {
+            let least_significant_bits = (remaining_bits as u8) & LSB_MASK;
+            // An arithmetic shift, so negative numbers fill up with ones
+            remaining_bits >>= 7;
+
+            // We're done once everything left is sign extension, and the sign bit we've just
+            // written agrees with it
+            let sign_bit_set = least_significant_bits & SIGN_BIT != 0;
+            if (remaining_bits == 0 && !sign_bit_set) || (remaining_bits == -1 && sign_bit_set) {
+                bytes[num_added_bytes] = least_significant_bits;
+                break num_added_bytes + 1;
+            }
+
+            bytes[num_added_bytes] = least_significant_bits | MSB_MASK;
+            num_added_bytes += 1;
+        }
+    }
+
+    fn encoded_len(n: i64) -> usize {
+        // The significant bits, plus one for the sign
+        let num_redundant_sign_bits = if n < 0 { !n } else { n }.leading_zeros() as usize;
+        (65 - num_redundant_sign_bits).div_ceil(7)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(i64, usize), DecodeError> {
+        let mut n: i64 = 0;
+
+        for (i, byte) in bytes.iter().take(Self::MAX_ENCODED_LEN).enumerate() {
+            let least_significant_bits = byte & LSB_MASK;
+            if i == Self::MAX_ENCODED_LEN - 1 {
+                if *byte >= MSB_MASK {
+                    return Err(DecodeError::Overlong);
+                }
+                // The tenth byte lands at bit 63, so its other six bits have to be copies of
+                // that one (all zeroes or all ones), or the value wouldn't fit in an i64
+                if least_significant_bits != 0 && least_significant_bits != LSB_MASK {
+                    return Err(DecodeError::Overflow);
+                }
+            }
+            n |= (least_significant_bits as i64) << (7 * i);
+
+            if *byte < MSB_MASK {
+                let num_bits = 7 * (i + 1);
+                if num_bits < 64 && least_significant_bits & SIGN_BIT != 0 {
+                    n |= -1 << num_bits;
+                }
+                return Ok((n, i + 1));
+            }
+        }
+
+        Err(DecodeError::Truncated)
+    }
+}
+
+/// SQLite's varint, used in record headers and b-tree pages. It's big-endian (most significant
+/// group first), so encoded values sort the same way as the numbers they hold. The first eight
+/// bytes carry seven bits each, but a ninth byte carries a full eight, so a u64 never takes
+/// more than nine bytes.
+pub struct Sqlite;
+
+// The largest value that fits in eight bytes of seven bits each
+const SQLITE_MAX_8_BYTE: u64 = (1 << 56) - 1;
+
+impl VarintFormat for Sqlite {
+    type Value = u64;
+    const MAX_ENCODED_LEN: usize = 9;
+
+    fn encode(n: u64, bytes: &mut [u8]) -> usize {
+        let num_bytes = Self::encoded_len(n);
+        let mut remaining_bits = n;
+
+        if num_bytes == 9 {
+            bytes[8] = remaining_bits as u8;
+            remaining_bits >>= 8;
+            for byte in bytes[..8].iter_mut().rev() {
+                *byte = (remaining_bits as u8 & LSB_MASK) | MSB_MASK;
+                remaining_bits >>= 7;
+            }
+            return 9;
+        }
+
+        // Fill in from the end, since the least significant group comes last
+        bytes[num_bytes - 1] = remaining_bits as u8 & LSB_MASK;
+        for byte in bytes[..num_bytes - 1].iter_mut().rev() {
+            remaining_bits >>= 7;
+            *byte = (remaining_bits as u8 & LSB_MASK) | MSB_MASK;
+        }
+        num_bytes
+    }
+
+    fn encoded_len(n: u64) -> usize {
+        if n > SQLITE_MAX_8_BYTE {
+            9
+        } else {
+            crate::encoded_len(n)
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
+        let mut n: u64 = 0;
+
+        for (i, byte) in bytes.iter().take(Self::MAX_ENCODED_LEN).enumerate() {
+            if i == 8 {
+                // The ninth byte has no continuation bit; all eight of its bits are payload
+                return Ok(((n << 8) | *byte as u64, 9));
+            }
+            n = (n << 7) | (byte & LSB_MASK) as u64;
+            if *byte < MSB_MASK {
+                return Ok((n, i + 1));
+            }
+        }
+
+        Err(DecodeError::Truncated)
+    }
+}
+
+/// PrefixVarint: the length goes up front, in unary, as the number of trailing zeroes in the
+/// first byte. So a decoder knows how many bytes to read from the first one alone, and can
+/// then load them all at once instead of checking a continuation bit per byte. Like LEB128 it
+/// holds seven bits per byte and is little-endian, up to eight bytes. A first byte of zero
+/// means the full 64 bits follow in the next eight bytes, for nine in total.
+pub struct PrefixVarint;
+
+impl VarintFormat for PrefixVarint {
+    type Value = u64;
+    const MAX_ENCODED_LEN: usize = 9;
+
+    fn encode(n: u64, bytes: &mut [u8]) -> usize {
+        let num_bytes = Self::encoded_len(n);
+        if num_bytes == 9 {
+            bytes[0] = 0;
+            bytes[1..9].copy_from_slice(&n.to_le_bytes());
+            return 9;
+        }
+
+        // `num_bytes - 1` zeroes and a one, then the value itself
+        let prefixed = (n << num_bytes) | (1 << (num_bytes - 1));
+        bytes[..num_bytes].copy_from_slice(&prefixed.to_le_bytes()[..num_bytes]);
+        num_bytes
+    }
+
+    fn encoded_len(n: u64) -> usize {
+        // Same cutoffs as SQLite's format: seven bits per byte, up to 56 bits
+        Sqlite::encoded_len(n)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
+        let Some(&first) = bytes.first() else {
+            return Err(DecodeError::Truncated);
+        };
+
+        if first == 0 {
+            let Some(value_bytes) = bytes.get(1..9) else {
+                return Err(DecodeError::Truncated);
+            };
+            return Ok((u64::from_le_bytes(value_bytes.try_into().unwrap()), 9));
+        }
+
+        let num_bytes = first.trailing_zeros() as usize + 1;
+        // With a whole word's worth of bytes left, we can load it in one go and mask off
+        // anything past the end of this value
+        if let Some(word_bytes) = bytes.get(..8) {
+            let word = u64::from_le_bytes(word_bytes.try_into().unwrap());
+            let mask = u64::MAX >> (64 - 8 * num_bytes);
+            return Ok(((word & mask) >> num_bytes, num_bytes));
+        }
+        let Some(prefixed_bytes) = bytes.get(..num_bytes) else {
+            return Err(DecodeError::Truncated);
+        };
+        let mut word = [0; 8];
+        word[..num_bytes].copy_from_slice(prefixed_bytes);
+        Ok((u64::from_le_bytes(word) >> num_bytes, num_bytes))
+    }
+}
@@ -0,0 +1,203 @@
+//! Length-delimited framing, as written by Java's `writeDelimitedTo` and C++'s
+//! `SerializeDelimitedToOstream`: each message in a stream is prefixed by its length as a
+//! varint, since protobuf messages don't mark where they end.
+
+use crate::DecodeError;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Protobuf's own default limit on how large a message it will parse. A corrupt or malicious
+/// length prefix could otherwise claim gigabytes, and have us try to buffer all of it.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Why a length-delimited stream couldn't be split into messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// The stream ended partway through a length prefix or a message.
+    UnexpectedEof { offset: usize },
+    /// A length prefix was too long, or too large to fit in a u64.
+    InvalidLength { offset: usize, error: DecodeError },
+    /// A length prefix claimed more bytes than the maximum message size.
+    TooLarge { offset: usize, len: u64, max: usize },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FrameError::*;
+        match self {
+            UnexpectedEof { offset } => write!(f, "unexpected end of stream at byte {offset}"),
+            InvalidLength { offset, error } => {
+                write!(f, "invalid length prefix at byte {offset}: {error}")
+            }
+            TooLarge { offset, len, max } => write!(
+                f,
+                "message at byte {offset} is {len} bytes, more than the maximum of {max}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for FrameError {}
+
+/// Iterator over the messages in a length-delimited stream that's already in memory, yielding
+/// each one as a slice of the original bytes. After the first error, iteration stops.
+pub struct Frames<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    max_message_size: usize,
+    failed: bool,
+}
+
+impl<'a> Frames<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            failed: false,
+        }
+    }
+
+    /// Reject any message longer than this many bytes, rather than `DEFAULT_MAX_MESSAGE_SIZE`.
+    pub fn max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    fn read_frame(&mut self) -> Result<&'a [u8], FrameError> {
+        let len_offset = self.offset;
+        let (len, num_bytes) =
+            crate::try_decode(&self.bytes[len_offset..]).map_err(|e| match e {
+                DecodeError::Truncated => FrameError::UnexpectedEof { offset: len_offset },
+                error => FrameError::InvalidLength {
+                    offset: len_offset,
+                    error,
+                },
+            })?;
+        check_len(len_offset, len, self.max_message_size)?;
+
+        // The maximum may be as large as usize::MAX, so the end of the message can be past
+        // anything we could index (or, on 32-bit targets, the length can be more than a usize)
+        let start = len_offset + num_bytes;
+        let message = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or(FrameError::UnexpectedEof {
+                offset: self.bytes.len(),
+            })?;
+        self.offset = start + message.len();
+        Ok(message)
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<&'a [u8], FrameError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.bytes.len() {
+            return None;
+        }
+        let result = self.read_frame();
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+fn check_len(offset: usize, len: u64, max: usize) -> Result<(), FrameError> {
+    if len > max as u64 {
+        return Err(FrameError::TooLarge { offset, len, max });
+    }
+    Ok(())
+}
+
+/// Appends the given message to `out`, prefixed by its length.
+#[cfg(feature = "alloc")]
+pub fn encode_frame(message: &[u8], out: &mut Vec<u8>) {
+    let (len_bytes, num_len_bytes) = crate::encode_to_array(message.len() as u64);
+    out.extend_from_slice(&len_bytes[..num_len_bytes]);
+    out.extend_from_slice(message);
+}
+
+/// Writes the given message to `writer`, prefixed by its length. Returns the total number of
+/// bytes written.
+#[cfg(feature = "std")]
+pub fn write_frame<W: Write + ?Sized>(writer: &mut W, message: &[u8]) -> io::Result<usize> {
+    let (len_bytes, num_len_bytes) = crate::encode_to_array(message.len() as u64);
+    writer.write_all(&len_bytes[..num_len_bytes])?;
+    writer.write_all(message)?;
+    Ok(num_len_bytes + message.len())
+}
+
+/// Reads length-delimited messages out of any `Read`, such as a file or socket, one at a time.
+/// Length prefixes are read a byte at a time, so unbuffered readers should be wrapped in a
+/// `BufReader` first. Errors are `UnexpectedEof` for a stream that ends partway through a
+/// message, and `InvalidData` wrapping a `FrameError` for anything else.
+#[cfg(feature = "std")]
+pub struct FrameReader<R> {
+    reader: R,
+    offset: usize,
+    max_message_size: usize,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            done: false,
+        }
+    }
+
+    /// Reject any message longer than this many bytes, rather than `DEFAULT_MAX_MESSAGE_SIZE`.
+    pub fn max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// Reads the next message, or returns `None` if the reader ran out of bytes exactly at the
+    /// end of the previous one.
+    pub fn read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let len_offset = self.offset;
+        let Some(len) = crate::io::read_varint_or_eof(&mut self.reader)? else {
+            return Ok(None);
+        };
+        check_len(len_offset, len, self.max_message_size)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        // `take` stops us reading past this message, and `read_to_end` only grows the buffer
+        // as bytes actually arrive, so a bogus length can't make us allocate it all up front
+        let mut message = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut message)?;
+        if message.len() as u64 != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        self.offset += crate::encoded_len(len) + message.len();
+        Ok(Some(message))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_frame().transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{DecodeError, LSB_MASK, MSB_MASK};
+
+/// Varint encoding for every primitive integer type, so callers can work with e.g. a u32 field
+/// value directly instead of casting to and from u64 (and risking silent truncation on the way
+/// back). Signed types are ZigZag-encoded, as for protobuf's `sint32`/`sint64` fields.
+pub trait Varint: Sized + Copy {
+    /// The most bytes an encoding of this type can take up.
+    const MAX_ENCODED_LEN: usize;
+
+    /// Adds encoded bytes to the given slice. Returns the number of bytes added.
+    fn encode_varint(self, bytes: &mut [u8]) -> usize;
+
+    /// The number of bytes `encode_varint` will add.
+    fn encoded_varint_len(self) -> usize;
+
+    /// Decodes the first varint in the given bytes slice, returning it along with the number of
+    /// bytes it was encoded in. Returns `DecodeError::Overflow` if it's too large for this type.
+    fn decode_varint(bytes: &[u8]) -> Result<(Self, usize), DecodeError>;
+}
+
+// Everything up to 64 bits can piggyback on the u64 functions, with a range check on the way out.
+macro_rules! impl_varint_for_unsigned {
+    ($($t:ty),*) => {$(
+        impl Varint for $t {
+            const MAX_ENCODED_LEN: usize = (<$t>::BITS as usize).div_ceil(7);
+
+            fn encode_varint(self, bytes: &mut [u8]) -> usize {
+                crate::encode(self as u64, bytes)
+            }
+
+            fn encoded_varint_len(self) -> usize {
+                crate::encoded_len(self as u64)
+            }
+
+            fn decode_varint(bytes: &[u8]) -> Result<(Self, usize), DecodeError> {
+                // Look no further than an encoding of this type can take up. If every byte
+                // there has a continuation bit, it's overlong rather than just truncated.
+                let bytes = &bytes[..bytes.len().min(Self::MAX_ENCODED_LEN)];
+                let (n, num_bytes) = match crate::try_decode(bytes) {
+                    Err(DecodeError::Truncated) if bytes.len() == Self::MAX_ENCODED_LEN => {
+                        return Err(DecodeError::Overlong)
+                    }
+                    result => result?,
+                };
+                let n = <$t>::try_from(n).map_err(|_| DecodeError::Overflow)?;
+                Ok((n, num_bytes))
+            }
+        }
+    )*};
+}
+
+impl_varint_for_unsigned!(u8, u16, u32, u64, usize);
+
+impl Varint for u128 {
+    const MAX_ENCODED_LEN: usize = (u128::BITS as usize).div_ceil(7);
+
+    fn encode_varint(self, bytes: &mut [u8]) -> usize {
+        // Same algorithm as `crate::encode`, with room for more bits
+        let mut num_added_bytes = 0;
+        let mut remaining_bits = self;
+
+        loop {
+            let least_significant_bits = (remaining_bits & LSB_MASK as u128) as u8;
+            remaining_bits >>= 7;
+
+            if remaining_bits == 0 {
+                bytes[num_added_bytes] = least_significant_bits;
+                break num_added_bytes + 1;
+            }
+
+            bytes[num_added_bytes] = least_significant_bits | MSB_MASK;
+            num_added_bytes += 1;
+        }
+    }
+
+    fn encoded_varint_len(self) -> usize {
+        let num_significant_bits = (u128::BITS - self.leading_zeros()) as usize;
+        num_significant_bits.div_ceil(7).max(1)
+    }
+
+    fn decode_varint(bytes: &[u8]) -> Result<(Self, usize), DecodeError> {
+        // Same algorithm as `crate::try_decode`, with room for more bits
+        let mut n: u128 = 0;
+
+        for (i, byte) in bytes.iter().take(Self::MAX_ENCODED_LEN).enumerate() {
+            let least_significant_bits = (byte & LSB_MASK) as u128;
+            if i == Self::MAX_ENCODED_LEN - 1 {
+                if *byte >= MSB_MASK {
+                    return Err(DecodeError::Overlong);
+                }
+                // The nineteenth byte lands at bit 126, so only its lowest two bits have room
+                if least_significant_bits > 0b11 {
+                    return Err(DecodeError::Overflow);
+                }
+            }
+            n |= least_significant_bits << (7 * i);
+
+            if *byte < MSB_MASK {
+                return Ok((n, i + 1));
+            }
+        }
+
+        Err(DecodeError::Truncated)
+    }
+}
+
+// See `crate::zigzag` for how this works
+macro_rules! zigzag_encode {
+    ($n:expr, $t:ty, $u:ty) => {
+        (($n << 1) ^ ($n >> (<$t>::BITS - 1))) as $u
+    };
+}
+
+// Signed types ZigZag-encode into the unsigned type of the same width, then defer to it.
+macro_rules! impl_varint_for_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl Varint for $t {
+            const MAX_ENCODED_LEN: usize = <$u as Varint>::MAX_ENCODED_LEN;
+
+            fn encode_varint(self, bytes: &mut [u8]) -> usize {
+                zigzag_encode!(self, $t, $u).encode_varint(bytes)
+            }
+
+            fn encoded_varint_len(self) -> usize {
+                zigzag_encode!(self, $t, $u).encoded_varint_len()
+            }
+
+            fn decode_varint(bytes: &[u8]) -> Result<(Self, usize), DecodeError> {
+                let (n, num_bytes) = <$u>::decode_varint(bytes)?;
+                Ok((((n >> 1) as $t) ^ -((n & 1) as $t), num_bytes))
+            }
+        }
+    )*};
+}
+
+impl_varint_for_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
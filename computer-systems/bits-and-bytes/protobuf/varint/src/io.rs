@@ -0,0 +1,118 @@
+use crate::{DecodeError, MAX_NUM_ENCODABLE_BYTES_FOR_U64};
+
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Reads varints straight out of any `Read`, such as a file or socket. Bytes are pulled one
+/// at a time (we can't know a varint's length until we've seen its last byte), so unbuffered
+/// readers should be wrapped in a `BufReader` first.
+pub trait ReadVarint: Read {
+    /// Reads exactly one varint. Running out of input partway through (or before the first
+    /// byte) is an `UnexpectedEof` error; a malformed varint is an `InvalidData` error that
+    /// wraps the underlying `DecodeError`.
+    fn read_varint(&mut self) -> io::Result<u64> {
+        read_varint_or_eof(self)?.ok_or_else(|| ErrorKind::UnexpectedEof.into())
+    }
+
+    /// Like `read_varint`, but for a ZigZag-encoded signed integer.
+    fn read_varint_signed(&mut self) -> io::Result<i64> {
+        self.read_varint().map(crate::zigzag::decode_i64)
+    }
+
+    /// Returns an iterator over every varint left in the reader, ending cleanly if the reader
+    /// runs out of bytes exactly at the end of a varint.
+    fn varints(self) -> Varints<Self>
+    where
+        Self: Sized,
+    {
+        Varints {
+            reader: self,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read + ?Sized> ReadVarint for R {}
+
+/// Writes varints to any `Write`, without the caller having to size a buffer for them.
+pub trait WriteVarint: Write {
+    /// Writes one varint, returning the number of bytes written.
+    fn write_varint(&mut self, n: u64) -> io::Result<usize> {
+        let mut bytes = [0; MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+        let num_bytes = crate::encode(n, &mut bytes);
+        self.write_all(&bytes[..num_bytes])?;
+        Ok(num_bytes)
+    }
+
+    /// Like `write_varint`, but ZigZag-encodes the given signed integer first.
+    fn write_varint_signed(&mut self, n: i64) -> io::Result<usize> {
+        self.write_varint(crate::zigzag::encode_i64(n))
+    }
+}
+
+impl<W: Write + ?Sized> WriteVarint for W {}
+
+/// Iterator over successive varints from a reader. See `ReadVarint::varints`. After the first
+/// error, iteration stops.
+pub struct Varints<R> {
+    reader: R,
+    done: bool,
+}
+
+impl<R> Varints<R> {
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Varints<R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = read_varint_or_eof(&mut self.reader).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+/// Reads one varint, or returns `None` if the reader was already exhausted before its first
+/// byte. That's the one case where running out of bytes isn't an error: it's how a stream of
+/// varints ends.
+pub(crate) fn read_varint_or_eof<R: Read + ?Sized>(reader: &mut R) -> io::Result<Option<u64>> {
+    let mut bytes = [0; MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+
+    for i in 0..MAX_NUM_ENCODABLE_BYTES_FOR_U64 {
+        if !read_byte(reader, &mut bytes[i])? {
+            return if i == 0 {
+                Ok(None)
+            } else {
+                Err(ErrorKind::UnexpectedEof.into())
+            };
+        }
+
+        match crate::try_decode(&bytes[..=i]) {
+            Ok((n, _)) => return Ok(Some(n)),
+            Err(DecodeError::Truncated) => continue, // haven't seen the last byte yet
+            Err(e) => return Err(io::Error::new(ErrorKind::InvalidData, e)),
+        }
+    }
+
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        DecodeError::Overlong,
+    ))
+}
+
+/// Reads a single byte into `byte`, returning false if the reader had nothing left.
+fn read_byte<R: Read + ?Sized>(reader: &mut R, byte: &mut u8) -> io::Result<bool> {
+    loop {
+        match reader.read(std::slice::from_mut(byte)) {
+            Ok(0) => return Ok(false),
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
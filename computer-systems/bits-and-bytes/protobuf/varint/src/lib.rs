@@ -1,3 +1,32 @@
+// Without the `std` feature, this crate only needs `core` (plus `alloc` for the handful of
+// functions that return a Vec), so it can run on embedded targets.
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod breakdown;
+#[cfg(feature = "alloc")]
+mod bulk;
+pub mod formats;
+pub mod framing;
+mod generic;
+#[cfg(feature = "std")]
+pub mod io;
+pub mod packed;
+pub mod wire;
+#[cfg(feature = "alloc")]
+pub mod writer;
+pub mod zigzag;
+
+#[cfg(feature = "alloc")]
+pub use bulk::decode_many;
+pub use generic::Varint;
+
+use core::fmt;
+
 const LSB_MASK: u8 = 0b01_11_11_11;
 const MSB_MASK: u8 = !LSB_MASK; // 0b10_00_00_00
 const LSB_MASK_U64: u64 = LSB_MASK as u64;
@@ -5,7 +34,7 @@ const LSB_MASK_U64: u64 = LSB_MASK as u64;
 pub const MAX_NUM_ENCODABLE_BYTES_FOR_U64: usize = 10;
 
 /// Adds encoded bytes to the given slice. Returns the number of bytes added.
-pub fn encode(n: u64, bytes: &mut [u8]) -> usize {
+pub const fn encode(n: u64, bytes: &mut [u8]) -> usize {
     let mut num_added_bytes = 0;
     let mut remaining_bits = n;
 
@@ -25,11 +54,82 @@ pub fn encode(n: u64, bytes: &mut [u8]) -> usize {
     }
 }
 
+/// Like `encode`, but returns the encoded bytes in a fixed-size array (along with how many of
+/// them were used) rather than writing to a slice. As a `const fn`, it can serialize constants
+/// at compile time:
+/// ```
+/// const ENCODED_150: ([u8; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64], usize) =
+///     varint::encode_to_array(150);
+/// assert_eq!(&ENCODED_150.0[..ENCODED_150.1], &[0x96, 0x01]);
+/// ```
+pub const fn encode_to_array(n: u64) -> ([u8; MAX_NUM_ENCODABLE_BYTES_FOR_U64], usize) {
+    let mut bytes = [0; MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    let num_bytes = encode(n, &mut bytes);
+    (bytes, num_bytes)
+}
+
+/// The number of bytes `encode` will add for the given integer.
+pub const fn encoded_len(n: u64) -> usize {
+    // Every started group of seven significant bits needs a byte, and even zero needs one.
+    let num_significant_bits = 64 - n.leading_zeros() as usize;
+    if num_significant_bits == 0 {
+        1
+    } else {
+        num_significant_bits.div_ceil(7)
+    }
+}
+
+/// The number of bytes `encode_signed` will add for the given integer.
+pub const fn encoded_len_signed(n: i64) -> usize {
+    encoded_len(zigzag::encode_i64(n))
+}
+
+/// ZigZag-encodes the given signed integer, then adds its varint bytes to the given slice.
+/// Returns the number of bytes added. Works for `sint32` values too, since ZigZag-encoding an
+/// i32 yields the same number as ZigZag-encoding it after sign-extending it to an i64.
+pub const fn encode_signed(n: i64, bytes: &mut [u8]) -> usize {
+    encode(zigzag::encode_i64(n), bytes)
+}
+
 /// Reads through the given bytes slice until it finds a byte without a continuation bit,
-/// effectively decoding the first varint that can be found. Panics if the slice is exhausted
-/// before encountering a byte without a continuation bit.
-/// NOTE: this could be updated to also return the number of bytes that were decoded.
+/// effectively decoding the first varint that can be found. Panics if the bytes don't begin
+/// with a valid varint; use `try_decode` to handle that case or to learn how many bytes the
+/// varint took up.
 pub fn decode(bytes: &[u8]) -> u64 {
+    match try_decode(bytes) {
+        Ok((n, _)) => n,
+        Err(e) => panic!("Failed to decode varint: {e}"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The slice ran out before we found a byte without a continuation bit.
+    Truncated,
+    /// Every byte that an encoding of the target integer type could take up (for a u64,
+    /// MAX_NUM_ENCODABLE_BYTES_FOR_U64) had a continuation bit set.
+    Overlong,
+    /// The varint was a valid length, but it's too large for the target integer type.
+    Overflow,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DecodeError::*;
+        match self {
+            Truncated => write!(f, "ran out of bytes without finding last byte of varint"),
+            Overlong => write!(f, "varint has too many continuation bytes for its type"),
+            Overflow => write!(f, "varint is too large for the target integer type"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Decodes the first varint in the given bytes slice, returning it along with the number of
+/// bytes it was encoded in. Any bytes after that are ignored, so a stream of consecutive
+/// varints can be decoded by re-slicing past each one in turn.
+pub fn try_decode(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
     let mut n: u64 = 0;
     let mut num_bits_already_added = 0;
 
@@ -40,17 +140,35 @@ pub fn decode(bytes: &[u8]) -> u64 {
     // int's least significant seven bits, then our second iteration yields the next-least-
     // significant seven, and so on. To make this work, each iteration uses a bit mask with
     // more and more zero-padding on its right.
-    for byte in bytes.iter() {
+    for (i, byte) in bytes
+        .iter()
+        .take(MAX_NUM_ENCODABLE_BYTES_FOR_U64)
+        .enumerate()
+    {
         let least_significant_bits = (byte & LSB_MASK) as u64;
+        if i == MAX_NUM_ENCODABLE_BYTES_FOR_U64 - 1 {
+            if *byte >= MSB_MASK {
+                return Err(DecodeError::Overlong);
+            }
+            // The tenth byte lands at bit 63, so only its lowest bit has room in a u64
+            if least_significant_bits > 1 {
+                return Err(DecodeError::Overflow);
+            }
+        }
         let new_bits_mask = least_significant_bits << num_bits_already_added;
         n |= new_bits_mask;
         num_bits_already_added += 7;
 
-        if byte < &MSB_MASK {
+        if *byte < MSB_MASK {
             // no continuation bit in this byte, so don't continue
-            return n;
+            return Ok((n, i + 1));
         }
     }
 
-    panic!("Ran out of bytes without finding last byte of varint!")
+    Err(DecodeError::Truncated)
+}
+
+/// Like `try_decode`, but for a ZigZag-encoded signed integer.
+pub fn try_decode_signed(bytes: &[u8]) -> Result<(i64, usize), DecodeError> {
+    try_decode(bytes).map(|(n, num_bytes)| (zigzag::decode_i64(n), num_bytes))
 }
@@ -0,0 +1,75 @@
+#[cfg(feature = "alloc")]
+use crate::DecodeError;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+// Protobuf packs a repeated scalar field into a single LEN record: the number of bytes in the
+// payload, as a varint, followed by every element's varint back-to-back. These functions deal
+// with that length prefix plus payload; the record's tag is up to the caller.
+
+/// The number of bytes the elements take up, not counting the length prefix.
+pub fn payload_len(values: &[u64]) -> usize {
+    values.iter().map(|&n| crate::encoded_len(n)).sum()
+}
+
+/// The total number of bytes `encode` will write for these values, length prefix included.
+pub fn encoded_len(values: &[u64]) -> usize {
+    let payload_len = payload_len(values);
+    crate::encoded_len(payload_len as u64) + payload_len
+}
+
+/// Adds the length-prefixed, packed values to the given slice. Returns the number of bytes
+/// added, which is always `encoded_len(values)`; panics if the slice is shorter than that.
+pub fn encode(values: &[u64], bytes: &mut [u8]) -> usize {
+    let mut num_added_bytes = crate::encode(payload_len(values) as u64, bytes);
+    for &n in values {
+        num_added_bytes += crate::encode(n, &mut bytes[num_added_bytes..]);
+    }
+    num_added_bytes
+}
+
+/// Reads a length prefix, then decodes every varint in the payload that follows it. Returns
+/// the values along with the total number of bytes read (prefix included). Any bytes after
+/// the payload are ignored.
+#[cfg(feature = "alloc")]
+pub fn decode(bytes: &[u8]) -> Result<(Vec<u64>, usize), DecodeError> {
+    let (payload_len, prefix_len) = crate::try_decode(bytes)?;
+    let payload = usize::try_from(payload_len)
+        .ok()
+        .and_then(|payload_len| bytes[prefix_len..].get(..payload_len))
+        .ok_or(DecodeError::Truncated)?;
+
+    // Every element takes at least one byte, so this is always enough room for all of them.
+    let mut values = Vec::with_capacity(payload.len());
+    crate::decode_many(payload, &mut values)?;
+    Ok((values, prefix_len + payload.len()))
+}
+
+/// Like `payload_len`, but for values that `encode_signed` will ZigZag-encode.
+pub fn payload_len_signed(values: &[i64]) -> usize {
+    values.iter().map(|&n| crate::encoded_len_signed(n)).sum()
+}
+
+/// Like `encoded_len`, but for values that `encode_signed` will ZigZag-encode.
+pub fn encoded_len_signed(values: &[i64]) -> usize {
+    let payload_len = payload_len_signed(values);
+    crate::encoded_len(payload_len as u64) + payload_len
+}
+
+/// Like `encode`, but ZigZag-encodes each value first, as for `sint32` and `sint64` fields.
+pub fn encode_signed(values: &[i64], bytes: &mut [u8]) -> usize {
+    let mut num_added_bytes = crate::encode(payload_len_signed(values) as u64, bytes);
+    for &n in values {
+        num_added_bytes += crate::encode_signed(n, &mut bytes[num_added_bytes..]);
+    }
+    num_added_bytes
+}
+
+/// Like `decode`, but for ZigZag-encoded values.
+#[cfg(feature = "alloc")]
+pub fn decode_signed(bytes: &[u8]) -> Result<(Vec<i64>, usize), DecodeError> {
+    let (values, num_bytes) = decode(bytes)?;
+    let values = values.into_iter().map(crate::zigzag::decode_i64).collect();
+    Ok((values, num_bytes))
+}
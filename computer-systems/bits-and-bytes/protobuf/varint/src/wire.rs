@@ -0,0 +1,233 @@
+use crate::DecodeError;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The six wire types a protobuf record can have, per
+/// https://protobuf.dev/programming-guides/encoding/#structure
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireType {
+    Varint,
+    I64,
+    Len,
+    SGroup, // deprecated
+    EGroup, // deprecated
+    I32,
+}
+
+impl WireType {
+    /// The three-bit id that gets packed into the bottom of every record's tag.
+    pub fn id(&self) -> u8 {
+        use WireType::*;
+        match self {
+            Varint => 0,
+            I64 => 1,
+            Len => 2,
+            SGroup => 3,
+            EGroup => 4,
+            I32 => 5,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        use WireType::*;
+        match id {
+            0 => Some(Varint),
+            1 => Some(I64),
+            2 => Some(Len),
+            3 => Some(SGroup),
+            4 => Some(EGroup),
+            5 => Some(I32),
+            _ => None,
+        }
+    }
+}
+
+/// The payload of a single record. Without a schema we can't know what a value actually
+/// means (is that varint an int32, a bool, or an enum?), so we only go as far as the wire
+/// format itself lets us. LEN payloads borrow from the message bytes rather than copying.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value<'a> {
+    Varint(u64),
+    I64(u64),
+    Len(&'a [u8]),
+    SGroup,
+    EGroup,
+    I32(u32),
+}
+
+/// One `(field_number, wire_type, value)` record from a protobuf message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field<'a> {
+    pub field_number: u32,
+    pub wire_type: WireType,
+    pub value: Value<'a>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WireError {
+    /// The message ended partway through a tag, varint or fixed-width value.
+    UnexpectedEof {
+        offset: usize,
+    },
+    /// A varint was too long, or too large to fit in a u64.
+    InvalidVarint {
+        offset: usize,
+        error: DecodeError,
+    },
+    InvalidWireType {
+        offset: usize,
+        id: u8,
+    },
+    InvalidFieldNumber {
+        offset: usize,
+        field_number: u64,
+    },
+    /// A LEN record claimed more bytes than remain in the message.
+    LengthOutOfBounds {
+        offset: usize,
+        len: u64,
+    },
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use WireError::*;
+        match self {
+            UnexpectedEof { offset } => write!(f, "unexpected end of message at byte {offset}"),
+            InvalidVarint { offset, error } => {
+                write!(f, "invalid varint at byte {offset}: {error}")
+            }
+            InvalidWireType { offset, id } => {
+                write!(f, "invalid wire type {id} in tag at byte {offset}")
+            }
+            InvalidFieldNumber {
+                offset,
+                field_number,
+            } => write!(
+                f,
+                "invalid field number {field_number} in tag at byte {offset}"
+            ),
+            LengthOutOfBounds { offset, len } => write!(
+                f,
+                "LEN record at byte {offset} claims {len} bytes, more than remain in message"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for WireError {}
+
+// Field numbers are 29 bits: the tag varint is a u32 with the wire type in its low 3 bits.
+pub const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
+
+/// Parse every record in the given message. Nested messages aren't recursed into (we can't
+/// tell them apart from strings or bytes without a schema); they come back as `Value::Len`
+/// slices that can themselves be passed to `parse`.
+#[cfg(feature = "alloc")]
+pub fn parse(bytes: &[u8]) -> Result<Vec<Field<'_>>, WireError> {
+    Fields::new(bytes).collect()
+}
+
+/// Iterator over the records in a protobuf message, yielding them one at a time. After the
+/// first error, iteration stops.
+pub struct Fields<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    fn read_field(&mut self) -> Result<Field<'a>, WireError> {
+        let tag_offset = self.offset;
+        let tag = self.read_varint()?;
+
+        let wire_type_id = (tag & 0b111) as u8;
+        let wire_type = WireType::from_id(wire_type_id).ok_or(WireError::InvalidWireType {
+            offset: tag_offset,
+            id: wire_type_id,
+        })?;
+
+        let field_number = tag >> 3;
+        if field_number == 0 || field_number > MAX_FIELD_NUMBER as u64 {
+            return Err(WireError::InvalidFieldNumber {
+                offset: tag_offset,
+                field_number,
+            });
+        }
+
+        let value = match wire_type {
+            WireType::Varint => Value::Varint(self.read_varint()?),
+            WireType::I64 => Value::I64(u64::from_le_bytes(self.read_fixed::<8>()?)),
+            WireType::Len => {
+                let len_offset = self.offset;
+                let len = self.read_varint()?;
+                let remaining = self.bytes.len() - self.offset;
+                if len > remaining as u64 {
+                    return Err(WireError::LengthOutOfBounds {
+                        offset: len_offset,
+                        len,
+                    });
+                }
+                let start = self.offset;
+                self.offset += len as usize;
+                Value::Len(&self.bytes[start..self.offset])
+            }
+            WireType::SGroup => Value::SGroup,
+            WireType::EGroup => Value::EGroup,
+            WireType::I32 => Value::I32(u32::from_le_bytes(self.read_fixed::<4>()?)),
+        };
+
+        Ok(Field {
+            field_number: field_number as u32,
+            wire_type,
+            value,
+        })
+    }
+
+    fn read_varint(&mut self) -> Result<u64, WireError> {
+        let start = self.offset;
+        let (n, num_bytes) = crate::try_decode(&self.bytes[start..]).map_err(|e| match e {
+            DecodeError::Truncated => WireError::UnexpectedEof { offset: start },
+            error => WireError::InvalidVarint {
+                offset: start,
+                error,
+            },
+        })?;
+        self.offset += num_bytes;
+        Ok(n)
+    }
+
+    fn read_fixed<const N: usize>(&mut self) -> Result<[u8; N], WireError> {
+        let start = self.offset;
+        let fixed: [u8; N] = self
+            .bytes
+            .get(start..start + N)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(WireError::UnexpectedEof { offset: start })?;
+        self.offset += N;
+        Ok(fixed)
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Field<'a>, WireError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.bytes.len() {
+            return None;
+        }
+        let result = self.read_field();
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::wire::{WireType, MAX_FIELD_NUMBER};
+use crate::MAX_NUM_ENCODABLE_BYTES_FOR_U64;
+
+use alloc::vec::Vec;
+
+/// Builds a protobuf message one field at a time, into a growable buffer. Every method
+/// returns the writer itself so calls can be chained:
+/// ```
+/// let bytes = varint::writer::MessageWriter::new()
+///     .varint(1, 150)
+///     .string(2, "testing")
+///     .start_message(3)
+///     .sint(1, -1)
+///     .end_message()
+///     .finish();
+/// assert_eq!(varint::wire::parse(&bytes).unwrap().len(), 3);
+/// ```
+/// Methods panic if given a field number outside 1 to `MAX_FIELD_NUMBER`, since that would
+/// only produce a message nobody could read.
+#[derive(Default)]
+pub struct MessageWriter {
+    bytes: Vec<u8>,
+    // Where each still-open nested message's payload starts. We can't know a nested message's
+    // length (which has to come first) until it's finished, so we leave a gap and fill in the
+    // length prefix once `end_message` is called.
+    open_messages: Vec<usize>,
+}
+
+impl MessageWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A VARINT field: `uint32`, `uint64`, `int32`, `int64`, `bool` or `enum`. Note that a
+    /// negative `int32` or `int64` must be cast to u64 (sign-extending it), which always takes
+    /// ten bytes; use `sint` for fields that are often negative.
+    pub fn varint(&mut self, field_number: u32, n: u64) -> &mut Self {
+        self.tag(field_number, WireType::Varint);
+        self.raw_varint(n);
+        self
+    }
+
+    /// A ZigZag-encoded VARINT field: `sint32` or `sint64`.
+    pub fn sint(&mut self, field_number: u32, n: i64) -> &mut Self {
+        self.varint(field_number, crate::zigzag::encode_i64(n))
+    }
+
+    /// An I32 field: `fixed32`, `sfixed32` (cast to u32) or `float` (via `f32::to_bits`).
+    pub fn fixed32(&mut self, field_number: u32, n: u32) -> &mut Self {
+        self.tag(field_number, WireType::I32);
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    /// An I64 field: `fixed64`, `sfixed64` (cast to u64) or `double` (via `f64::to_bits`).
+    pub fn fixed64(&mut self, field_number: u32, n: u64) -> &mut Self {
+        self.tag(field_number, WireType::I64);
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    /// A LEN field holding arbitrary bytes.
+    pub fn bytes(&mut self, field_number: u32, bytes: &[u8]) -> &mut Self {
+        self.tag(field_number, WireType::Len);
+        self.raw_varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// A LEN field holding a string.
+    pub fn string(&mut self, field_number: u32, s: &str) -> &mut Self {
+        self.bytes(field_number, s.as_bytes())
+    }
+
+    /// A LEN field holding a packed repeated VARINT field. See `crate::packed`.
+    pub fn packed(&mut self, field_number: u32, values: &[u64]) -> &mut Self {
+        self.tag(field_number, WireType::Len);
+        let start = self.bytes.len();
+        self.bytes
+            .resize(start + crate::packed::encoded_len(values), 0);
+        crate::packed::encode(values, &mut self.bytes[start..]);
+        self
+    }
+
+    /// Starts a nested message in the given field. Every field written until the matching
+    /// `end_message` call goes inside it; nested messages can themselves be nested.
+    pub fn start_message(&mut self, field_number: u32) -> &mut Self {
+        self.tag(field_number, WireType::Len);
+        self.open_messages.push(self.bytes.len());
+        self
+    }
+
+    /// Ends the most recently started nested message, by going back and inserting its length
+    /// in front of it. Panics if there's no nested message to end.
+    pub fn end_message(&mut self) -> &mut Self {
+        let start = self
+            .open_messages
+            .pop()
+            .expect("end_message called without a matching start_message");
+        let (len_bytes, num_len_bytes) = crate::encode_to_array((self.bytes.len() - start) as u64);
+        self.bytes
+            .splice(start..start, len_bytes[..num_len_bytes].iter().copied());
+        self
+    }
+
+    /// Writes a nested message in the given field, with whatever fields the closure writes.
+    /// Shorthand for `start_message`, then the closure, then `end_message`.
+    pub fn message(
+        &mut self,
+        field_number: u32,
+        write_fields: impl FnOnce(&mut Self) -> &mut Self,
+    ) -> &mut Self {
+        self.start_message(field_number);
+        write_fields(self);
+        self.end_message()
+    }
+
+    /// Returns the finished message, leaving this writer empty so it can be reused. Panics if
+    /// a nested message was started but never ended.
+    pub fn finish(&mut self) -> Vec<u8> {
+        assert!(
+            self.open_messages.is_empty(),
+            "finish called with {} nested message(s) still open",
+            self.open_messages.len()
+        );
+        core::mem::take(&mut self.bytes)
+    }
+
+    fn tag(&mut self, field_number: u32, wire_type: WireType) {
+        assert!(
+            (1..=MAX_FIELD_NUMBER).contains(&field_number),
+            "field number {field_number} is out of range"
+        );
+        self.raw_varint(((field_number as u64) << 3) | wire_type.id() as u64);
+    }
+
+    fn raw_varint(&mut self, n: u64) {
+        let mut bytes = [0; MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+        let num_bytes = crate::encode(n, &mut bytes);
+        self.bytes.extend_from_slice(&bytes[..num_bytes]);
+    }
+}
@@ -0,0 +1,22 @@
+// ZigZag encoding, used by protobuf's `sint32` and `sint64` types. Casting a negative number
+// straight to an unsigned one sets its most significant bit, so even -1 would take up the
+// maximum ten varint bytes. ZigZag instead interleaves negative and positive numbers
+// (0 => 0, -1 => 1, 1 => 2, -2 => 3, ...), so that small magnitudes stay small either way.
+
+pub const fn encode_i32(n: i32) -> u32 {
+    // The arithmetic right shift smears the sign bit across every bit: all ones for a negative
+    // number, all zeroes otherwise. XORing with that flips every bit of negative numbers.
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+pub const fn decode_i32(n: u32) -> i32 {
+    ((n >> 1) as i32) ^ -((n & 1) as i32)
+}
+
+pub const fn encode_i64(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+pub const fn decode_i64(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
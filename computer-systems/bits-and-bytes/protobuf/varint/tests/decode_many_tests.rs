@@ -0,0 +1,77 @@
+/// Encodes the given numbers back-to-back, the way a packed repeated field would.
+fn encode_all(numbers: &[u64]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut buf = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    for &n in numbers {
+        let num_bytes = varint::encode(n, &mut buf);
+        bytes.extend_from_slice(&buf[..num_bytes]);
+    }
+    bytes
+}
+
+#[test]
+fn test_decode_many_every_length() {
+    // The largest and smallest values for every possible encoded length, one to ten bytes
+    let mut numbers = vec![0];
+    for num_bits in (7..64).step_by(7) {
+        numbers.push((1 << num_bits) - 1);
+        numbers.push(1 << num_bits);
+    }
+    numbers.push(u64::MAX);
+
+    let mut decoded = Vec::new();
+    assert_eq!(
+        varint::decode_many(&encode_all(&numbers), &mut decoded),
+        Ok(numbers.len())
+    );
+    assert_eq!(decoded, numbers);
+}
+
+#[test]
+fn test_decode_many_matches_try_decode() {
+    // A deterministic mix of every magnitude, via a simple linear congruential generator
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let numbers: Vec<u64> = (0..5000)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            state >> (state % 64)
+        })
+        .collect();
+    let bytes = encode_all(&numbers);
+
+    let mut decoded = Vec::new();
+    varint::decode_many(&bytes, &mut decoded).unwrap();
+    assert_eq!(decoded, numbers);
+}
+
+#[test]
+fn test_decode_many_appends() {
+    let mut decoded = vec![7];
+    assert_eq!(varint::decode_many(&[0x96, 0x01], &mut decoded), Ok(1));
+    assert_eq!(decoded, vec![7, 150]);
+}
+
+#[test]
+fn test_decode_many_empty() {
+    let mut decoded = Vec::new();
+    assert_eq!(varint::decode_many(&[], &mut decoded), Ok(0));
+    assert!(decoded.is_empty());
+}
+
+#[test]
+fn test_decode_many_errors() {
+    let mut bytes = encode_all(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    bytes.push(0x96);
+    let mut decoded = Vec::new();
+    assert_eq!(
+        varint::decode_many(&bytes, &mut decoded),
+        Err(varint::DecodeError::Truncated)
+    );
+    assert_eq!(decoded, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    let mut decoded = Vec::new();
+    assert_eq!(
+        varint::decode_many(&[0x80; 16], &mut decoded),
+        Err(varint::DecodeError::Overlong)
+    );
+}
@@ -0,0 +1,175 @@
+use varint::formats::{Leb128, PrefixVarint, SignedLeb128, Sqlite, VarintFormat};
+use varint::DecodeError;
+
+fn encode<F: VarintFormat>(n: F::Value) -> Vec<u8> {
+    let mut buf = [0; 16];
+    let num_bytes = F::encode(n, &mut buf);
+    assert_eq!(num_bytes, F::encoded_len(n));
+    assert!(num_bytes <= F::MAX_ENCODED_LEN);
+    buf[..num_bytes].to_vec()
+}
+
+fn assert_encodes<F: VarintFormat>(n: F::Value, expected: &[u8])
+where
+    F::Value: PartialEq + std::fmt::Debug,
+{
+    assert_eq!(encode::<F>(n), expected, "encoding {n:?}");
+    assert_eq!(
+        F::decode(expected),
+        Ok((n, expected.len())),
+        "decoding {n:?}"
+    );
+}
+
+// Every length boundary for 7-bit groups, plus the extremes
+fn boundaries() -> Vec<u64> {
+    let mut values = vec![0, 1, u64::MAX];
+    for bits in (7..64).step_by(7) {
+        values.extend([(1 << bits) - 1, 1 << bits]);
+    }
+    values
+}
+
+fn assert_round_trips<F: VarintFormat<Value = u64>>() {
+    for n in boundaries() {
+        let bytes = encode::<F>(n);
+        assert_eq!(F::decode(&bytes), Ok((n, bytes.len())), "{n}");
+        // Trailing bytes are left alone
+        let mut padded = bytes.clone();
+        padded.extend_from_slice(&[0xff; 10]);
+        assert_eq!(F::decode(&padded), Ok((n, bytes.len())), "{n}");
+        // And every truncation is caught
+        for len in 0..bytes.len() {
+            assert_eq!(F::decode(&bytes[..len]), Err(DecodeError::Truncated), "{n}");
+        }
+    }
+}
+
+#[test]
+fn test_leb128() {
+    // Examples from the DWARF 5 spec, section 7.6
+    assert_encodes::<Leb128>(2, &[0x02]);
+    assert_encodes::<Leb128>(127, &[0x7f]);
+    assert_encodes::<Leb128>(128, &[0x80, 0x01]);
+    assert_encodes::<Leb128>(129, &[0x81, 0x01]);
+    assert_encodes::<Leb128>(130, &[0x82, 0x01]);
+    assert_encodes::<Leb128>(12857, &[0xb9, 0x64]);
+    assert_round_trips::<Leb128>();
+}
+
+#[test]
+fn test_signed_leb128() {
+    // Also from the DWARF 5 spec
+    assert_encodes::<SignedLeb128>(2, &[0x02]);
+    assert_encodes::<SignedLeb128>(-2, &[0x7e]);
+    assert_encodes::<SignedLeb128>(127, &[0xff, 0x00]);
+    assert_encodes::<SignedLeb128>(-127, &[0x81, 0x7f]);
+    assert_encodes::<SignedLeb128>(128, &[0x80, 0x01]);
+    assert_encodes::<SignedLeb128>(-128, &[0x80, 0x7f]);
+    assert_encodes::<SignedLeb128>(129, &[0x81, 0x01]);
+    assert_encodes::<SignedLeb128>(-129, &[0xff, 0x7e]);
+
+    assert_encodes::<SignedLeb128>(0, &[0x00]);
+    assert_encodes::<SignedLeb128>(-1, &[0x7f]);
+    assert_encodes::<SignedLeb128>(63, &[0x3f]);
+    assert_encodes::<SignedLeb128>(64, &[0xc0, 0x00]);
+    assert_encodes::<SignedLeb128>(-64, &[0x40]);
+    assert_encodes::<SignedLeb128>(-65, &[0xbf, 0x7f]);
+    assert_encodes::<SignedLeb128>(
+        i64::MAX,
+        &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00],
+    );
+    assert_encodes::<SignedLeb128>(
+        i64::MIN,
+        &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f],
+    );
+
+    for bits in 0..64 {
+        for n in [
+            1i64 << bits,
+            (1i64 << bits).wrapping_sub(1),
+            (1i64 << bits).wrapping_neg(),
+        ] {
+            let bytes = encode::<SignedLeb128>(n);
+            assert_eq!(SignedLeb128::decode(&bytes), Ok((n, bytes.len())), "{n}");
+        }
+    }
+}
+
+#[test]
+fn test_signed_leb128_errors() {
+    let continuation = [0x80; 10];
+    assert_eq!(
+        SignedLeb128::decode(&continuation),
+        Err(DecodeError::Overlong)
+    );
+    assert_eq!(
+        SignedLeb128::decode(&continuation[..9]),
+        Err(DecodeError::Truncated)
+    );
+    // 2^63: too large for an i64, even though it fits in ten bytes
+    let too_large = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+    assert_eq!(SignedLeb128::decode(&too_large), Err(DecodeError::Overflow));
+}
+
+#[test]
+fn test_sqlite() {
+    assert_encodes::<Sqlite>(0, &[0x00]);
+    assert_encodes::<Sqlite>(127, &[0x7f]);
+    // Big-endian, so the high bits come first
+    assert_encodes::<Sqlite>(128, &[0x81, 0x00]);
+    assert_encodes::<Sqlite>(300, &[0x82, 0x2c]);
+    assert_encodes::<Sqlite>(16383, &[0xff, 0x7f]);
+    assert_encodes::<Sqlite>(16384, &[0x81, 0x80, 0x00]);
+    assert_encodes::<Sqlite>(
+        (1 << 56) - 1,
+        &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+    );
+    // Past 56 bits, the ninth byte holds eight bits rather than seven, which shifts the
+    // rest over by one
+    assert_encodes::<Sqlite>(
+        1 << 56,
+        &[0x80, 0xc0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00],
+    );
+    assert_encodes::<Sqlite>(u64::MAX, &[0xff; 9]);
+    assert_round_trips::<Sqlite>();
+}
+
+#[test]
+fn test_prefix_varint() {
+    assert_encodes::<PrefixVarint>(0, &[0x01]);
+    assert_encodes::<PrefixVarint>(127, &[0xff]);
+    // One trailing zero in the first byte means two bytes
+    assert_encodes::<PrefixVarint>(128, &[0x02, 0x02]);
+    assert_encodes::<PrefixVarint>(300, &[0xb2, 0x04]);
+    assert_encodes::<PrefixVarint>(16384, &[0x04, 0x00, 0x02]);
+    assert_encodes::<PrefixVarint>(
+        (1 << 56) - 1,
+        &[0x80, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+    );
+    assert_encodes::<PrefixVarint>(
+        1 << 56,
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+    );
+    assert_encodes::<PrefixVarint>(
+        u64::MAX,
+        &[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+    );
+    assert_round_trips::<PrefixVarint>();
+}
+
+#[test]
+fn test_density() {
+    // Encoded lengths at each format's most interesting cutoffs
+    let lengths = |n: u64| {
+        [
+            Leb128::encoded_len(n),
+            Sqlite::encoded_len(n),
+            PrefixVarint::encoded_len(n),
+        ]
+    };
+    assert_eq!(lengths(127), [1, 1, 1]);
+    assert_eq!(lengths(1 << 56), [9, 9, 9]);
+    assert_eq!(lengths(1 << 63), [10, 9, 9]);
+    assert_eq!(lengths(u64::MAX), [10, 9, 9]);
+}
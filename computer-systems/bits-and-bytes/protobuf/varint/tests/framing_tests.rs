@@ -0,0 +1,130 @@
+use std::io::{Cursor, ErrorKind};
+use varint::framing::{encode_frame, write_frame, FrameError, FrameReader, Frames};
+use varint::DecodeError;
+
+fn framed(messages: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for message in messages {
+        encode_frame(message, &mut bytes);
+    }
+    bytes
+}
+
+#[test]
+fn test_encode_frame() {
+    let long = [0xAA; 300];
+    let bytes = framed(&[&[0x08, 0x96, 0x01], &[], &long]);
+    assert_eq!(&bytes[..6], &[0x03, 0x08, 0x96, 0x01, 0x00, 0xAC]);
+    assert_eq!(bytes[6], 0x02); // 300 = 0xAC 0x02
+    assert_eq!(bytes.len(), 4 + 1 + 2 + 300);
+}
+
+#[test]
+fn test_write_frame_matches_encode_frame() {
+    let mut written = Vec::new();
+    assert_eq!(write_frame(&mut written, &[1, 2, 3]).unwrap(), 4);
+    assert_eq!(write_frame(&mut written, &[]).unwrap(), 1);
+    assert_eq!(written, framed(&[&[1, 2, 3], &[]]));
+}
+
+#[test]
+fn test_frames() {
+    let bytes = framed(&[&[0x08, 0x96, 0x01], &[], &[0x10, 0x02]]);
+    let messages: Vec<_> = Frames::new(&bytes).collect::<Result<_, _>>().unwrap();
+    assert_eq!(messages, vec![&[0x08, 0x96, 0x01][..], &[], &[0x10, 0x02]]);
+
+    assert_eq!(Frames::new(&[]).count(), 0);
+}
+
+#[test]
+fn test_frames_errors() {
+    // Stops after the first error
+    let mut frames = Frames::new(&[0x01, 0xAA, 0x05, 0xBB]);
+    assert_eq!(frames.next(), Some(Ok(&[0xAA][..])));
+    assert_eq!(
+        frames.next(),
+        Some(Err(FrameError::UnexpectedEof { offset: 4 }))
+    );
+    assert_eq!(frames.next(), None);
+
+    assert_eq!(
+        Frames::new(&[0x01, 0xAA, 0x80]).nth(1),
+        Some(Err(FrameError::UnexpectedEof { offset: 2 }))
+    );
+    assert_eq!(
+        Frames::new(&[0x80; 11]).next(),
+        Some(Err(FrameError::InvalidLength {
+            offset: 0,
+            error: DecodeError::Overlong
+        }))
+    );
+}
+
+#[test]
+fn test_max_message_size() {
+    let bytes = framed(&[&[0; 4], &[0; 5]]);
+    let mut frames = Frames::new(&bytes).max_message_size(4);
+    assert_eq!(frames.next(), Some(Ok(&[0; 4][..])));
+    assert_eq!(
+        frames.next(),
+        Some(Err(FrameError::TooLarge {
+            offset: 5,
+            len: 5,
+            max: 4
+        }))
+    );
+
+    // A huge claimed length is rejected before we look for that many bytes
+    assert_eq!(
+        Frames::new(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]).next(),
+        Some(Err(FrameError::TooLarge {
+            offset: 0,
+            len: u32::MAX as u64,
+            max: varint::framing::DEFAULT_MAX_MESSAGE_SIZE
+        }))
+    );
+
+    // With no maximum to stop it, the largest possible length mustn't overflow when we work
+    // out where the message ends
+    let u64_max_len = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+    assert_eq!(
+        Frames::new(&u64_max_len)
+            .max_message_size(usize::MAX)
+            .next(),
+        Some(Err(FrameError::UnexpectedEof { offset: 10 }))
+    );
+}
+
+#[test]
+fn test_frame_reader() {
+    let bytes = framed(&[&[0x08, 0x96, 0x01], &[], &[0x10, 0x02]]);
+    let messages: Vec<_> = FrameReader::new(Cursor::new(bytes))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(messages, vec![vec![0x08, 0x96, 0x01], vec![], vec![0x10, 0x02]]);
+}
+
+#[test]
+fn test_frame_reader_errors() {
+    let mut reader = FrameReader::new(Cursor::new(vec![0x01, 0xAA, 0x05, 0xBB]));
+    assert_eq!(reader.next().unwrap().unwrap(), vec![0xAA]);
+    assert_eq!(
+        reader.next().unwrap().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+    assert!(reader.next().is_none());
+
+    let bytes = framed(&[&[0; 4], &[0; 5]]);
+    let mut reader = FrameReader::new(Cursor::new(bytes)).max_message_size(4);
+    assert_eq!(reader.read_frame().unwrap(), Some(vec![0; 4]));
+    let error = reader.read_frame().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        error.into_inner().unwrap().downcast::<FrameError>().unwrap(),
+        Box::new(FrameError::TooLarge {
+            offset: 5,
+            len: 5,
+            max: 4
+        })
+    );
+}
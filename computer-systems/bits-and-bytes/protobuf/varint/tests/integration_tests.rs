@@ -1,5 +1,3 @@
-use varint;
-
 #[test]
 fn test_0() {
     let mut bytes: Vec<u8> = vec![0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
@@ -48,3 +46,142 @@ fn test_max_int() {
     let decoded = varint::decode(&bytes);
     assert_eq!(decoded, initial);
 }
+
+#[test]
+fn test_try_decode_reports_length() {
+    assert_eq!(varint::try_decode(&[0x00]), Ok((0, 1)));
+    assert_eq!(varint::try_decode(&[0x96, 0x01]), Ok((150, 2)));
+    assert_eq!(
+        varint::try_decode(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+        Ok((u64::MAX, 10))
+    );
+}
+
+#[test]
+fn test_try_decode_consecutive_varints() {
+    let bytes = [0x96, 0x01, 0x7F, 0x80, 0x02];
+    let mut offset = 0;
+    let mut decoded = Vec::new();
+    while offset < bytes.len() {
+        let (n, num_bytes) = varint::try_decode(&bytes[offset..]).unwrap();
+        decoded.push(n);
+        offset += num_bytes;
+    }
+    assert_eq!(decoded, vec![150, 127, 256]);
+}
+
+#[test]
+fn test_try_decode_truncated() {
+    assert_eq!(varint::try_decode(&[]), Err(varint::DecodeError::Truncated));
+    assert_eq!(
+        varint::try_decode(&[0x96, 0x81]),
+        Err(varint::DecodeError::Truncated)
+    );
+}
+
+#[test]
+fn test_try_decode_overlong() {
+    assert_eq!(
+        varint::try_decode(&[0x80; 11]),
+        Err(varint::DecodeError::Overlong)
+    );
+    // Even an eleventh byte that would have ended the varint doesn't save it
+    let mut bytes = vec![0xFF; 10];
+    bytes.push(0x00);
+    assert_eq!(
+        varint::try_decode(&bytes),
+        Err(varint::DecodeError::Overlong)
+    );
+}
+
+#[test]
+fn test_try_decode_overflow() {
+    assert_eq!(
+        varint::try_decode(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02]),
+        Err(varint::DecodeError::Overflow)
+    );
+}
+
+#[test]
+#[should_panic(expected = "ran out of bytes")]
+fn test_decode_panics_when_truncated() {
+    varint::decode(&[0x96]);
+}
+
+#[test]
+fn test_zigzag_i32() {
+    let cases: [(i32, u32); 7] = [
+        (0, 0),
+        (-1, 1),
+        (1, 2),
+        (-2, 3),
+        (63, 126),
+        (i32::MAX, u32::MAX - 1),
+        (i32::MIN, u32::MAX),
+    ];
+    for (signed, unsigned) in cases {
+        assert_eq!(varint::zigzag::encode_i32(signed), unsigned);
+        assert_eq!(varint::zigzag::decode_i32(unsigned), signed);
+    }
+}
+
+#[test]
+fn test_zigzag_i64() {
+    let cases: [(i64, u64); 7] = [
+        (0, 0),
+        (-1, 1),
+        (1, 2),
+        (-2, 3),
+        (2147483647, 4294967294),
+        (i64::MAX, u64::MAX - 1),
+        (i64::MIN, u64::MAX),
+    ];
+    for (signed, unsigned) in cases {
+        assert_eq!(varint::zigzag::encode_i64(signed), unsigned);
+        assert_eq!(varint::zigzag::decode_i64(unsigned), signed);
+    }
+}
+
+#[test]
+fn test_signed_negative_one() {
+    let mut bytes: Vec<u8> = vec![0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    let num_bytes = varint::encode_signed(-1, &mut bytes);
+    assert_eq!(&bytes[..num_bytes], &[0x01]);
+    assert_eq!(varint::try_decode_signed(&bytes), Ok((-1, 1)));
+}
+
+#[test]
+fn test_signed_min_int() {
+    let mut bytes: Vec<u8> = vec![0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    let num_bytes = varint::encode_signed(i64::MIN, &mut bytes);
+    assert_eq!(num_bytes, varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64);
+    assert_eq!(varint::try_decode_signed(&bytes), Ok((i64::MIN, 10)));
+}
+
+#[test]
+fn test_breakdown() {
+    let breakdown = varint::breakdown::Breakdown(&[0x96, 0x01]).to_string();
+    assert_eq!(
+        breakdown,
+        "96  1 0010110  (more bytes follow)\n01  0 0000001  (last byte)"
+    );
+}
+
+// Serialized at compile time
+const ENCODED_MAX_INT: ([u8; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64], usize) =
+    varint::encode_to_array(u64::MAX);
+
+#[test]
+fn test_encode_to_array() {
+    assert_eq!(
+        ENCODED_MAX_INT,
+        (
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            10
+        )
+    );
+    assert_eq!(varint::encode_to_array(0), ([0; 10], 1));
+
+    let (bytes, num_bytes) = varint::encode_to_array(150);
+    assert_eq!(&bytes[..num_bytes], &[0x96, 0x01]);
+}
@@ -0,0 +1,98 @@
+use std::io::{Cursor, ErrorKind};
+use varint::io::{ReadVarint, WriteVarint};
+
+#[test]
+fn test_write_then_read() {
+    let mut buf = Vec::new();
+    assert_eq!(buf.write_varint(150).unwrap(), 2);
+    assert_eq!(buf.write_varint(u64::MAX).unwrap(), 10);
+    assert_eq!(buf.write_varint_signed(-2).unwrap(), 1);
+    assert_eq!(
+        buf,
+        [0x96, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x03]
+    );
+
+    let mut reader = Cursor::new(buf);
+    assert_eq!(reader.read_varint().unwrap(), 150);
+    assert_eq!(reader.read_varint().unwrap(), u64::MAX);
+    assert_eq!(reader.read_varint_signed().unwrap(), -2);
+    assert_eq!(
+        reader.read_varint().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_read_leaves_following_bytes() {
+    let mut reader = Cursor::new(vec![0x96, 0x01, 0xAA]);
+    assert_eq!(reader.read_varint().unwrap(), 150);
+    assert_eq!(reader.position(), 2);
+}
+
+#[test]
+fn test_read_truncated() {
+    let mut reader = Cursor::new(vec![0x96]);
+    assert_eq!(
+        reader.read_varint().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_read_overlong() {
+    let mut reader = Cursor::new(vec![0x80; 11]);
+    let err = reader.read_varint().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.into_inner()
+            .unwrap()
+            .downcast::<varint::DecodeError>()
+            .unwrap()
+            .as_ref(),
+        &varint::DecodeError::Overlong
+    );
+}
+
+#[test]
+fn test_varints_iterator() {
+    let reader = Cursor::new(vec![0x00, 0x96, 0x01, 0x7F]);
+    let decoded: Vec<u64> = reader.varints().map(Result::unwrap).collect();
+    assert_eq!(decoded, vec![0, 150, 127]);
+}
+
+#[test]
+fn test_varints_iterator_empty() {
+    let reader = Cursor::new(Vec::new());
+    assert_eq!(reader.varints().count(), 0);
+}
+
+#[test]
+fn test_varints_iterator_stops_after_error() {
+    let reader = Cursor::new(vec![0x01, 0x96]);
+    let mut varints = reader.varints();
+    assert_eq!(varints.next().unwrap().unwrap(), 1);
+    assert_eq!(
+        varints.next().unwrap().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+    assert!(varints.next().is_none());
+}
+
+#[test]
+fn test_length_delimited_records() {
+    // Two records, each prefixed with its length as a varint
+    let mut file = Vec::new();
+    for record in [&b"hello"[..], &b"varint"[..]] {
+        file.write_varint(record.len() as u64).unwrap();
+        file.extend_from_slice(record);
+    }
+
+    let mut reader = Cursor::new(file);
+    let mut records = Vec::new();
+    while let Ok(len) = reader.read_varint() {
+        let mut record = vec![0; len as usize];
+        std::io::Read::read_exact(&mut reader, &mut record).unwrap();
+        records.push(record);
+    }
+    assert_eq!(records, vec![b"hello".to_vec(), b"varint".to_vec()]);
+}
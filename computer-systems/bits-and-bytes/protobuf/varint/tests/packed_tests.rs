@@ -0,0 +1,85 @@
+use varint::packed;
+use varint::DecodeError;
+
+#[test]
+fn test_encoded_len() {
+    assert_eq!(varint::encoded_len(0), 1);
+    assert_eq!(varint::encoded_len(127), 1);
+    assert_eq!(varint::encoded_len(128), 2);
+    assert_eq!(varint::encoded_len(u64::MAX), 10);
+    assert_eq!(varint::encoded_len_signed(-1), 1);
+    assert_eq!(varint::encoded_len_signed(-65), 2);
+    assert_eq!(varint::encoded_len_signed(i64::MIN), 10);
+
+    // Check every length boundary against what `encode` actually does
+    let mut bytes = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    for num_bits in 0..64 {
+        for n in [(1u64 << num_bits) - 1, 1 << num_bits] {
+            assert_eq!(varint::encoded_len(n), varint::encode(n, &mut bytes));
+        }
+    }
+}
+
+#[test]
+fn test_encode_packed() {
+    // The example from the protobuf docs: a packed field holding 3, 270 and 86942
+    let values = [3, 270, 86942];
+    assert_eq!(packed::payload_len(&values), 6);
+    assert_eq!(packed::encoded_len(&values), 7);
+
+    let mut bytes = vec![0; packed::encoded_len(&values)];
+    assert_eq!(packed::encode(&values, &mut bytes), 7);
+    assert_eq!(bytes, vec![0x06, 0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05]);
+}
+
+#[test]
+fn test_packed_roundtrip() {
+    let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+    let mut bytes = vec![0; packed::encoded_len(&values)];
+    let num_bytes = packed::encode(&values, &mut bytes);
+    assert_eq!(packed::decode(&bytes), Ok((values.to_vec(), num_bytes)));
+}
+
+#[test]
+fn test_packed_signed_roundtrip() {
+    let values = [0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+    let mut bytes = vec![0; packed::encoded_len_signed(&values)];
+    let num_bytes = packed::encode_signed(&values, &mut bytes);
+    assert_eq!(num_bytes, bytes.len());
+    assert_eq!(packed::payload_len_signed(&values), num_bytes - 1);
+    assert_eq!(
+        packed::decode_signed(&bytes),
+        Ok((values.to_vec(), num_bytes))
+    );
+}
+
+#[test]
+fn test_packed_empty() {
+    let mut bytes = vec![0; packed::encoded_len(&[])];
+    assert_eq!(packed::encode(&[], &mut bytes), 1);
+    assert_eq!(bytes, vec![0x00]);
+    assert_eq!(packed::decode(&bytes), Ok((vec![], 1)));
+}
+
+#[test]
+fn test_packed_decode_ignores_trailing_bytes() {
+    assert_eq!(
+        packed::decode(&[0x02, 0x01, 0x02, 0x03]),
+        Ok((vec![1, 2], 3))
+    );
+}
+
+#[test]
+fn test_packed_decode_errors() {
+    // length prefix promises more bytes than there are
+    assert_eq!(
+        packed::decode(&[0x03, 0x01, 0x02]),
+        Err(DecodeError::Truncated)
+    );
+    // last element runs off the end of the payload, even though more bytes follow it
+    assert_eq!(
+        packed::decode(&[0x02, 0x01, 0x96, 0x01]),
+        Err(DecodeError::Truncated)
+    );
+    assert_eq!(packed::decode(&[]), Err(DecodeError::Truncated));
+}
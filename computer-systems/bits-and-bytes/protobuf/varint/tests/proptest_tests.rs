@@ -0,0 +1,144 @@
+// Property-based tests: rather than a handful of fixed cases, proptest generates hundreds of
+// inputs per test (biased towards edge cases like 0 and u64::MAX), and shrinks any failure
+// down to a minimal example.
+
+use proptest::prelude::*;
+use std::panic;
+use varint::formats::{PrefixVarint, SignedLeb128, Sqlite, VarintFormat};
+use varint::wire;
+
+/// Numbers right around every 7-bit threshold, where an encoding gains or loses a byte: for
+/// each k, a few values either side of 2^(7k).
+fn threshold_values() -> impl Strategy<Value = u64> {
+    (1u32..=9, -3i64..=3).prop_map(|(k, delta)| (1u64 << (7 * k)).wrapping_add_signed(delta))
+}
+
+fn encode_all(numbers: &[u64]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut buf = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    for &n in numbers {
+        let num_bytes = varint::encode(n, &mut buf);
+        bytes.extend_from_slice(&buf[..num_bytes]);
+    }
+    bytes
+}
+
+proptest! {
+    #[test]
+    fn roundtrip(n in any::<u64>()) {
+        let mut bytes = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+        let num_bytes = varint::encode(n, &mut bytes);
+        prop_assert_eq!(num_bytes, varint::encoded_len(n));
+        prop_assert_eq!(varint::decode(&bytes), n);
+        prop_assert_eq!(varint::try_decode(&bytes[..num_bytes]), Ok((n, num_bytes)));
+    }
+
+    #[test]
+    fn roundtrip_thresholds(n in threshold_values()) {
+        let mut bytes = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+        let num_bytes = varint::encode(n, &mut bytes);
+        prop_assert_eq!(num_bytes, varint::encoded_len(n));
+        prop_assert_eq!(varint::try_decode(&bytes[..num_bytes]), Ok((n, num_bytes)));
+    }
+
+    #[test]
+    fn roundtrip_signed(n in any::<i64>()) {
+        let mut bytes = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+        let num_bytes = varint::encode_signed(n, &mut bytes);
+        prop_assert_eq!(num_bytes, varint::encoded_len_signed(n));
+        prop_assert_eq!(varint::try_decode_signed(&bytes), Ok((n, num_bytes)));
+    }
+
+    #[test]
+    fn roundtrip_zigzag_i32(n in any::<i32>()) {
+        prop_assert_eq!(varint::zigzag::decode_i32(varint::zigzag::encode_i32(n)), n);
+        // sint32 values can safely go through the 64-bit functions too
+        prop_assert_eq!(
+            varint::zigzag::encode_i64(n as i64),
+            varint::zigzag::encode_i32(n) as u64
+        );
+    }
+
+    #[test]
+    fn roundtrip_many(numbers in prop::collection::vec(
+        prop_oneof![any::<u64>(), threshold_values(), 0u64..128],
+        0..64,
+    )) {
+        let bytes = encode_all(&numbers);
+        let mut decoded = Vec::new();
+        prop_assert_eq!(varint::decode_many(&bytes, &mut decoded), Ok(numbers.len()));
+        prop_assert_eq!(&decoded, &numbers);
+
+        let mut packed = vec![0; varint::packed::encoded_len(&numbers)];
+        let num_bytes = varint::packed::encode(&numbers, &mut packed);
+        prop_assert_eq!(varint::packed::decode(&packed), Ok((numbers, num_bytes)));
+    }
+
+    /// Pins down exactly which inputs make `decode` panic: those (and only those) that
+    /// `try_decode` rejects. That's a truncated varint, one whose first ten bytes all have
+    /// continuation bits (overlong), or one whose tenth byte sets bits beyond the 64th
+    /// (overflow).
+    #[test]
+    fn decode_panics_exactly_when_try_decode_errors(bytes in prop::collection::vec(any::<u8>(), 0..16)) {
+        let expected = varint::try_decode(&bytes);
+        if let Ok((n, num_bytes)) = expected {
+            prop_assert!((1..=varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64).contains(&num_bytes));
+            // Non-minimal encodings (like 0x80 0x00 for zero) are accepted, so re-encoding can
+            // only ever come out the same length or shorter
+            prop_assert!(varint::encoded_len(n) <= num_bytes);
+        }
+
+        let decoded = panic::catch_unwind(|| varint::decode(&bytes));
+        prop_assert_eq!(decoded.ok(), expected.ok().map(|(n, _)| n));
+    }
+
+    #[test]
+    fn decode_many_matches_try_decode(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let mut expected = Vec::new();
+        let mut offset = 0;
+        let expected_result = loop {
+            if offset == bytes.len() {
+                break Ok(expected.len());
+            }
+            match varint::try_decode(&bytes[offset..]) {
+                Ok((n, num_bytes)) => {
+                    expected.push(n);
+                    offset += num_bytes;
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut decoded = Vec::new();
+        prop_assert_eq!(varint::decode_many(&bytes, &mut decoded), expected_result);
+        prop_assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn wire_parse_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let _ = wire::parse(&bytes);
+    }
+
+    #[test]
+    fn roundtrip_formats(n in any::<u64>(), signed in any::<i64>()) {
+        let mut bytes = [0; 10];
+        let num_bytes = Sqlite::encode(n, &mut bytes);
+        prop_assert_eq!(num_bytes, Sqlite::encoded_len(n));
+        prop_assert_eq!(Sqlite::decode(&bytes[..num_bytes]), Ok((n, num_bytes)));
+
+        let num_bytes = PrefixVarint::encode(n, &mut bytes);
+        prop_assert_eq!(num_bytes, PrefixVarint::encoded_len(n));
+        prop_assert_eq!(PrefixVarint::decode(&bytes[..num_bytes]), Ok((n, num_bytes)));
+
+        let num_bytes = SignedLeb128::encode(signed, &mut bytes);
+        prop_assert_eq!(num_bytes, SignedLeb128::encoded_len(signed));
+        prop_assert_eq!(SignedLeb128::decode(&bytes[..num_bytes]), Ok((signed, num_bytes)));
+    }
+
+    #[test]
+    fn formats_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..16)) {
+        let _ = Sqlite::decode(&bytes);
+        let _ = PrefixVarint::decode(&bytes);
+        let _ = SignedLeb128::decode(&bytes);
+    }
+}
@@ -0,0 +1,113 @@
+use varint::{DecodeError, Varint};
+
+/// Encodes with the trait, checks the length against `encoded_varint_len`, then decodes
+/// back into the same type.
+fn roundtrip<T: Varint + PartialEq + std::fmt::Debug>(n: T) -> Vec<u8> {
+    let mut bytes = vec![0; T::MAX_ENCODED_LEN];
+    let num_bytes = n.encode_varint(&mut bytes);
+    assert_eq!(num_bytes, n.encoded_varint_len());
+    assert_eq!(T::decode_varint(&bytes), Ok((n, num_bytes)));
+    bytes.truncate(num_bytes);
+    bytes
+}
+
+#[test]
+fn test_max_encoded_len() {
+    assert_eq!(u8::MAX_ENCODED_LEN, 2);
+    assert_eq!(u16::MAX_ENCODED_LEN, 3);
+    assert_eq!(u32::MAX_ENCODED_LEN, 5);
+    assert_eq!(
+        u64::MAX_ENCODED_LEN,
+        varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64
+    );
+    assert_eq!(u128::MAX_ENCODED_LEN, 19);
+    assert_eq!(i32::MAX_ENCODED_LEN, 5);
+    assert_eq!(i128::MAX_ENCODED_LEN, 19);
+    assert_eq!(usize::MAX_ENCODED_LEN, u64::MAX_ENCODED_LEN);
+}
+
+#[test]
+fn test_unsigned_roundtrips() {
+    assert_eq!(roundtrip(150u32), vec![0x96, 0x01]);
+    assert_eq!(roundtrip(u8::MAX), vec![0xFF, 0x01]);
+    assert_eq!(roundtrip(u16::MAX).len(), 3);
+    assert_eq!(roundtrip(u32::MAX).len(), 5);
+    assert_eq!(roundtrip(u64::MAX).len(), 10);
+    assert_eq!(roundtrip(usize::MAX).len(), usize::MAX_ENCODED_LEN);
+    assert_eq!(roundtrip(0u128), vec![0x00]);
+    assert_eq!(roundtrip(u128::MAX).len(), 19);
+    for num_bits in 0..128 {
+        roundtrip(1u128 << num_bits);
+        roundtrip((1u128 << num_bits) - 1);
+    }
+}
+
+#[test]
+fn test_matches_u64_encoding() {
+    // Narrower types produce exactly the same bytes as the u64 functions
+    let mut expected = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    let num_bytes = varint::encode(u32::MAX as u64, &mut expected);
+    assert_eq!(roundtrip(u32::MAX), &expected[..num_bytes]);
+
+    let num_bytes = varint::encode(u64::MAX, &mut expected);
+    assert_eq!(roundtrip(u64::MAX as u128), &expected[..num_bytes]);
+}
+
+#[test]
+fn test_signed_roundtrips() {
+    assert_eq!(roundtrip(-1i32), vec![0x01]);
+    assert_eq!(roundtrip(1i8), vec![0x02]);
+    assert_eq!(roundtrip(i8::MIN), vec![0xFF, 0x01]);
+    assert_eq!(roundtrip(i16::MIN).len(), 3);
+    assert_eq!(roundtrip(i32::MIN).len(), 5);
+    assert_eq!(roundtrip(i64::MIN).len(), 10);
+    assert_eq!(roundtrip(i128::MIN).len(), 19);
+    assert_eq!(roundtrip(i128::MAX).len(), 19);
+    roundtrip(isize::MIN);
+
+    // Same bytes as the i64 ZigZag functions
+    let mut expected = [0; varint::MAX_NUM_ENCODABLE_BYTES_FOR_U64];
+    let num_bytes = varint::encode_signed(i32::MIN as i64, &mut expected);
+    assert_eq!(roundtrip(i32::MIN), &expected[..num_bytes]);
+}
+
+#[test]
+fn test_decode_overflow_into_narrower_type() {
+    // 256 doesn't fit in a u8
+    assert_eq!(u8::decode_varint(&[0x80, 0x02]), Err(DecodeError::Overflow));
+    // u32::MAX + 1
+    assert_eq!(
+        u32::decode_varint(&[0x80, 0x80, 0x80, 0x80, 0x10]),
+        Err(DecodeError::Overflow)
+    );
+    // ZigZag-encoded i32::MAX + 1
+    assert_eq!(
+        i32::decode_varint(&[0x80, 0x80, 0x80, 0x80, 0x20]),
+        Err(DecodeError::Overflow)
+    );
+    // 2^128
+    let mut bytes = vec![0x80; 18];
+    bytes.push(0x04);
+    assert_eq!(u128::decode_varint(&bytes), Err(DecodeError::Overflow));
+}
+
+#[test]
+fn test_decode_errors() {
+    assert_eq!(u32::decode_varint(&[0x96]), Err(DecodeError::Truncated));
+    assert_eq!(u128::decode_varint(&[0x96]), Err(DecodeError::Truncated));
+    assert_eq!(u128::decode_varint(&[0x80; 19]), Err(DecodeError::Overlong));
+    assert_eq!(u16::decode_varint(&[0x80; 3]), Err(DecodeError::Overlong));
+    assert_eq!(u16::decode_varint(&[0x80; 2]), Err(DecodeError::Truncated));
+    assert_eq!(u8::decode_varint(&[0xFF; 2]), Err(DecodeError::Overlong));
+    assert_eq!(i32::decode_varint(&[0x80; 5]), Err(DecodeError::Overlong));
+    // Stops at the type's own limit, rather than reading as far as a u64 could go
+    assert_eq!(
+        u16::decode_varint(&[0x80, 0x80, 0x80, 0x00]),
+        Err(DecodeError::Overlong)
+    );
+    // The same error for every width, so the message can't give a number of bytes
+    assert_eq!(
+        u16::decode_varint(&[0x80; 3]).unwrap_err().to_string(),
+        "varint has too many continuation bytes for its type"
+    );
+}
@@ -0,0 +1,153 @@
+use varint::wire::{self, Field, Value, WireError, WireType};
+use varint::DecodeError;
+
+#[test]
+fn test_single_varint_field() {
+    // The canonical example from the protobuf docs: field 1 set to 150
+    let bytes = [0x08, 0x96, 0x01];
+    assert_eq!(
+        wire::parse(&bytes),
+        Ok(vec![Field {
+            field_number: 1,
+            wire_type: WireType::Varint,
+            value: Value::Varint(150),
+        }])
+    );
+}
+
+#[test]
+fn test_len_field() {
+    // field 2 set to the string "testing"
+    let bytes = [0x12, 0x07, 0x74, 0x65, 0x73, 0x74, 0x69, 0x6e, 0x67];
+    assert_eq!(
+        wire::parse(&bytes),
+        Ok(vec![Field {
+            field_number: 2,
+            wire_type: WireType::Len,
+            value: Value::Len(b"testing"),
+        }])
+    );
+}
+
+#[test]
+fn test_all_wire_types() {
+    let bytes = [
+        0x08, 0x01, // field 1, VARINT 1
+        0x11, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, // field 2, I64
+        0x1a, 0x02, 0x08, 0x01, // field 3, LEN containing a nested message
+        0x23, // field 4, SGROUP
+        0x2c, // field 5, EGROUP
+        0x35, 0xff, 0xff, 0xff, 0xff, // field 6, I32
+    ];
+    let fields = wire::parse(&bytes).unwrap();
+    let values: Vec<(u32, WireType, Value)> = fields
+        .into_iter()
+        .map(|f| (f.field_number, f.wire_type, f.value))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (1, WireType::Varint, Value::Varint(1)),
+            (2, WireType::I64, Value::I64(0x8000_0000_0000_0001)),
+            (3, WireType::Len, Value::Len(&[0x08, 0x01])),
+            (4, WireType::SGroup, Value::SGroup),
+            (5, WireType::EGroup, Value::EGroup),
+            (6, WireType::I32, Value::I32(u32::MAX)),
+        ]
+    );
+}
+
+#[test]
+fn test_nested_message_can_be_reparsed() {
+    let bytes = [0x1a, 0x03, 0x08, 0x96, 0x01];
+    let fields = wire::parse(&bytes).unwrap();
+    let Value::Len(nested) = fields[0].value else {
+        panic!("expected a LEN value");
+    };
+    assert_eq!(
+        wire::parse(nested),
+        Ok(vec![Field {
+            field_number: 1,
+            wire_type: WireType::Varint,
+            value: Value::Varint(150),
+        }])
+    );
+}
+
+#[test]
+fn test_large_field_number() {
+    // field number 2^29 - 1 (the maximum), VARINT 0
+    let bytes = [0xf8, 0xff, 0xff, 0xff, 0x0f, 0x00];
+    let fields = wire::parse(&bytes).unwrap();
+    assert_eq!(fields[0].field_number, (1 << 29) - 1);
+}
+
+#[test]
+fn test_empty_message() {
+    assert_eq!(wire::parse(&[]), Ok(vec![]));
+}
+
+#[test]
+fn test_truncated_varint() {
+    assert_eq!(
+        wire::parse(&[0x08, 0x96]),
+        Err(WireError::UnexpectedEof { offset: 1 })
+    );
+}
+
+#[test]
+fn test_truncated_fixed() {
+    assert_eq!(
+        wire::parse(&[0x0d, 0x01, 0x02]),
+        Err(WireError::UnexpectedEof { offset: 1 })
+    );
+}
+
+#[test]
+fn test_varint_too_long() {
+    let mut bytes = vec![0x08];
+    bytes.extend([0xff; 10]);
+    bytes.push(0x01);
+    assert_eq!(
+        wire::parse(&bytes),
+        Err(WireError::InvalidVarint {
+            offset: 1,
+            error: DecodeError::Overlong
+        })
+    );
+}
+
+#[test]
+fn test_invalid_wire_type() {
+    assert_eq!(
+        wire::parse(&[0x0e]),
+        Err(WireError::InvalidWireType { offset: 0, id: 6 })
+    );
+}
+
+#[test]
+fn test_field_number_zero() {
+    assert_eq!(
+        wire::parse(&[0x00, 0x01]),
+        Err(WireError::InvalidFieldNumber {
+            offset: 0,
+            field_number: 0
+        })
+    );
+}
+
+#[test]
+fn test_len_out_of_bounds() {
+    assert_eq!(
+        wire::parse(&[0x12, 0x05, 0x61]),
+        Err(WireError::LengthOutOfBounds { offset: 1, len: 5 })
+    );
+}
+
+#[test]
+fn test_iterator_stops_after_error() {
+    let mut fields = wire::Fields::new(&[0x08, 0x01, 0x0e, 0x08, 0x01]);
+    assert!(fields.next().unwrap().is_ok());
+    assert!(fields.next().unwrap().is_err());
+    assert!(fields.next().is_none());
+}
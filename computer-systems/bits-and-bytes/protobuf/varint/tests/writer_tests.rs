@@ -0,0 +1,133 @@
+use varint::wire::{self, Value};
+use varint::writer::MessageWriter;
+
+#[test]
+fn test_scalar_fields() {
+    let bytes = MessageWriter::new()
+        .varint(1, 150)
+        .sint(2, -2)
+        .fixed32(3, 1.0f32.to_bits())
+        .fixed64(4, 1.0f64.to_bits())
+        .finish();
+    assert_eq!(
+        bytes,
+        vec![
+            0x08, 0x96, 0x01, // field 1, VARINT 150
+            0x10, 0x03, // field 2, ZigZag-encoded -2
+            0x1d, 0x00, 0x00, 0x80, 0x3f, // field 3, I32
+            0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f, // field 4, I64
+        ]
+    );
+}
+
+#[test]
+fn test_len_fields() {
+    let bytes = MessageWriter::new()
+        .string(2, "testing")
+        .bytes(3, &[0xff])
+        .packed(4, &[3, 270, 86942])
+        .finish();
+    assert_eq!(
+        bytes,
+        vec![
+            0x12, 0x07, 0x74, 0x65, 0x73, 0x74, 0x69, 0x6e, 0x67, // field 2, "testing"
+            0x1a, 0x01, 0xff, // field 3, bytes
+            0x22, 0x06, 0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05, // field 4, packed
+        ]
+    );
+}
+
+#[test]
+fn test_nested_messages() {
+    let bytes = MessageWriter::new()
+        .start_message(3)
+        .varint(1, 150)
+        .start_message(2)
+        .string(1, "inner")
+        .end_message()
+        .end_message()
+        .varint(4, 1)
+        .finish();
+
+    let fields = wire::parse(&bytes).unwrap();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[1].value, Value::Varint(1));
+    let Value::Len(outer) = fields[0].value else {
+        panic!("expected a nested message");
+    };
+    let outer_fields = wire::parse(outer).unwrap();
+    assert_eq!(outer_fields[0].value, Value::Varint(150));
+    let Value::Len(inner) = outer_fields[1].value else {
+        panic!("expected a nested message");
+    };
+    assert_eq!(wire::parse(inner).unwrap()[0].value, Value::Len(b"inner"));
+}
+
+#[test]
+fn test_message_closure_matches_start_end() {
+    let with_closure = MessageWriter::new()
+        .message(1, |m| m.varint(1, 1).message(2, |m| m.varint(1, 2)))
+        .finish();
+    let with_start_end = MessageWriter::new()
+        .start_message(1)
+        .varint(1, 1)
+        .start_message(2)
+        .varint(1, 2)
+        .end_message()
+        .end_message()
+        .finish();
+    assert_eq!(with_closure, with_start_end);
+    assert_eq!(
+        with_closure,
+        vec![0x0a, 0x06, 0x08, 0x01, 0x12, 0x02, 0x08, 0x02]
+    );
+}
+
+#[test]
+fn test_long_nested_message_length_prefix() {
+    // A nested message over 127 bytes needs a two-byte length prefix
+    let long_string = "x".repeat(200);
+    let bytes = MessageWriter::new()
+        .message(1, |m| m.string(1, &long_string))
+        .finish();
+    assert_eq!(&bytes[..3], &[0x0a, 0xcb, 0x01]); // 203 = tag + 2 length bytes + 200
+    let fields = wire::parse(&bytes).unwrap();
+    let Value::Len(nested) = fields[0].value else {
+        panic!("expected a nested message");
+    };
+    assert_eq!(
+        wire::parse(nested).unwrap()[0].value,
+        Value::Len(long_string.as_bytes())
+    );
+}
+
+#[test]
+fn test_empty_nested_message() {
+    let bytes = MessageWriter::new().start_message(1).end_message().finish();
+    assert_eq!(bytes, vec![0x0a, 0x00]);
+}
+
+#[test]
+fn test_finish_resets_writer() {
+    let mut writer = MessageWriter::new();
+    assert_eq!(writer.varint(1, 1).finish(), vec![0x08, 0x01]);
+    assert_eq!(writer.varint(2, 2).finish(), vec![0x10, 0x02]);
+}
+
+#[test]
+#[should_panic(expected = "still open")]
+fn test_unclosed_message_panics() {
+    MessageWriter::new().start_message(1).finish();
+}
+
+#[test]
+#[should_panic(expected = "without a matching start_message")]
+fn test_unmatched_end_panics() {
+    MessageWriter::new().end_message();
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn test_field_number_zero_panics() {
+    MessageWriter::new().varint(0, 1);
+}